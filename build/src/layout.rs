@@ -0,0 +1,260 @@
+use crate::mli::TypeDef;
+use crate::stubs::{
+    has_explicit_tags, is_derived, ocaml_param, ocaml_params, stored_fields, variant_tags,
+};
+use crate::types::snake_case;
+
+/// Layout of a Rust type as produced by the derive macros
+enum Layout {
+    Record {
+        fields: Vec<String>,
+        floats: bool,
+    },
+    Tuple(usize),
+
+    /// Constructors with their arity and tag, `tagged` is set when tags are explicit, in which case
+    /// the OCaml type may have constructors that aren't defined in Rust
+    Variant {
+        constructors: Vec<(String, usize, usize)>,
+        tagged: bool,
+    },
+    Polymorphic(Vec<(String, usize)>),
+
+    /// Unboxed types have the layout of their field, which isn't checked
+    Unboxed,
+}
+
+fn variants(e: &syn::ItemEnum) -> Vec<(String, usize)> {
+    e.variants
+        .iter()
+        .map(|variant| {
+            (
+                variant.ident.to_string(),
+                stored_fields(&variant.fields).len(),
+            )
+        })
+        .collect()
+}
+
+/// Collect the layout of derived types, along with their OCaml names
+fn collect(items: &[syn::Item], layouts: &mut Vec<(String, Layout)>) {
+    for item in items {
+        match item {
+            syn::Item::Struct(s) if is_derived(&s.attrs) => {
+                let params = ocaml_params(&s.attrs);
+                let fields = stored_fields(&s.fields);
+                let layout = if ocaml_param(&params, "unboxed").is_some() {
+                    Layout::Unboxed
+                } else {
+                    match &s.fields {
+                        syn::Fields::Named(_) => Layout::Record {
+                            fields: fields
+                                .iter()
+                                .filter_map(|f| f.ident.as_ref())
+                                .map(|ident| ident.to_string().trim_start_matches("r#").to_string())
+                                .collect(),
+                            floats: ocaml_param(&params, "floats_array").is_some(),
+                        },
+                        _ => Layout::Tuple(fields.len()),
+                    }
+                };
+                layouts.push((snake_case(&s.ident.to_string()), layout));
+            }
+            syn::Item::Enum(e) if is_derived(&e.attrs) => {
+                let params = ocaml_params(&e.attrs);
+                let layout = if ocaml_param(&params, "polymorphic").is_some() {
+                    Layout::Polymorphic(variants(e))
+                } else {
+                    Layout::Variant {
+                        constructors: variants(e)
+                            .into_iter()
+                            .zip(variant_tags(e))
+                            .map(|((name, arity), (_, tag))| (name, arity, tag))
+                            .collect(),
+                        tagged: has_explicit_tags(e),
+                    }
+                };
+                layouts.push((snake_case(&e.ident.to_string()), layout));
+            }
+            syn::Item::Mod(m) => {
+                if let Some((_, items)) = &m.content {
+                    collect(items, layouts)
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+fn check_count(ocaml: usize, rust: usize, errors: &mut Vec<String>) -> bool {
+    if ocaml != rust {
+        errors.push(format!(
+            "OCaml defines {} constructors but Rust defines {}",
+            ocaml, rust
+        ));
+    }
+    ocaml == rust
+}
+
+/// Find the constructor `name` in `ocaml`, checking its arity
+fn find_constructor(
+    ocaml: &[(String, usize)],
+    name: &str,
+    arity: usize,
+    errors: &mut Vec<String>,
+) -> Option<usize> {
+    match ocaml.iter().position(|(n, _)| n == name) {
+        Some(i) if ocaml[i].1 != arity => {
+            errors.push(format!(
+                "constructor {} has {} arguments in OCaml but {} in Rust",
+                name, ocaml[i].1, arity
+            ));
+            None
+        }
+        Some(i) => Some(i),
+        None => {
+            errors.push(format!("constructor {} is not defined in OCaml", name));
+            None
+        }
+    }
+}
+
+fn compare_variants(
+    ocaml: &[(String, usize)],
+    rust: &[(String, usize, usize)],
+    tagged: bool,
+    errors: &mut Vec<String>,
+) {
+    if !tagged && !check_count(ocaml.len(), rust.len(), errors) {
+        return;
+    }
+
+    // Constant constructors and constructors with arguments are numbered separately
+    let ocaml_tag = |i: usize| {
+        let is_block = ocaml[i].1 > 0;
+        ocaml[..i]
+            .iter()
+            .filter(|(_, a)| (*a > 0) == is_block)
+            .count()
+    };
+    for (name, arity, tag) in rust {
+        if let Some(i) = find_constructor(ocaml, name, *arity, errors) {
+            if ocaml_tag(i) != *tag {
+                errors.push(format!(
+                    "constructor {} has tag {} in OCaml but {} in Rust",
+                    name,
+                    ocaml_tag(i),
+                    tag
+                ));
+            }
+        }
+    }
+}
+
+fn compare_polymorphic(
+    ocaml: &[(String, usize)],
+    rust: &[(String, usize)],
+    errors: &mut Vec<String>,
+) {
+    if check_count(ocaml.len(), rust.len(), errors) {
+        for (name, arity) in rust {
+            find_constructor(ocaml, name, *arity, errors);
+        }
+    }
+}
+
+fn compare(ocaml: &TypeDef, rust: &Layout, errors: &mut Vec<String>) {
+    match (ocaml, rust) {
+        (_, Layout::Unboxed) | (TypeDef::Other, _) => (),
+        (
+            TypeDef::Record {
+                fields: a,
+                floats: a_floats,
+            },
+            Layout::Record {
+                fields: b,
+                floats: b_floats,
+            },
+        ) => {
+            if a.len() != b.len() {
+                errors.push(format!(
+                    "OCaml defines {} fields but Rust stores {}",
+                    a.len(),
+                    b.len()
+                ));
+            } else {
+                for (i, (a, b)) in a.iter().zip(b.iter()).enumerate() {
+                    if a != b {
+                        errors.push(format!("field {} is {} in OCaml but {} in Rust", i, a, b));
+                    }
+                }
+            }
+            match (a_floats, b_floats) {
+                (true, false) => errors.push(
+                    "OCaml stores this record as a float array, use #[ocaml(floats_array)]"
+                        .to_string(),
+                ),
+                (false, true) => errors.push(
+                    "#[ocaml(floats_array)] is used but the OCaml record isn't a float record"
+                        .to_string(),
+                ),
+                _ => (),
+            }
+        }
+        // A single unnamed field is stored like a constructor with one argument
+        (TypeDef::Variant(a), Layout::Tuple(1)) if a.len() == 1 => {
+            if a[0].1 != 1 {
+                errors.push(format!(
+                    "constructor {} has {} arguments in OCaml but Rust stores 1",
+                    a[0].0, a[0].1
+                ));
+            }
+        }
+        (TypeDef::Tuple(a), Layout::Tuple(b)) => {
+            if a != b {
+                errors.push(format!(
+                    "OCaml tuple has {} elements but Rust stores {}",
+                    a, b
+                ));
+            }
+        }
+        (
+            TypeDef::Variant(a),
+            Layout::Variant {
+                constructors,
+                tagged,
+            },
+        ) => compare_variants(a, constructors, *tagged, errors),
+        (TypeDef::Polymorphic(a), Layout::Polymorphic(b)) => compare_polymorphic(a, b, errors),
+        (ocaml, _) => errors.push(format!(
+            "OCaml type is {} but Rust derives a different representation",
+            match ocaml {
+                TypeDef::Record { .. } => "a record",
+                TypeDef::Variant(_) => "a variant",
+                TypeDef::Polymorphic(_) => "a polymorphic variant",
+                TypeDef::Tuple(_) => "a tuple",
+                TypeDef::Other => unreachable!(),
+            }
+        )),
+    }
+}
+
+/// Compare the derived Rust types in `files` with the OCaml type definitions in `source`, returning
+/// a description of each mismatch. Types that are only defined on one side are ignored
+pub(crate) fn check(files: &[syn::File], source: &str) -> Vec<String> {
+    let mut layouts = Vec::new();
+    for file in files {
+        collect(&file.items, &mut layouts);
+    }
+
+    let defs = crate::mli::parse(source);
+    let mut errors = Vec::new();
+    for (name, layout) in &layouts {
+        if let Some((_, def)) = defs.iter().find(|(n, _)| n == name) {
+            let mut e = Vec::new();
+            compare(def, layout, &mut e);
+            errors.extend(e.into_iter().map(|e| format!("type {}: {}", name, e)));
+        }
+    }
+    errors
+}
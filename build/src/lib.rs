@@ -0,0 +1,204 @@
+#![deny(missing_docs)]
+
+//! `ocaml-build` generates the OCaml side of an `ocaml-rs` library from its Rust sources, keeping
+//! `external` declarations and type definitions in sync with the Rust code.
+//!
+//! - Functions marked with `#[ocaml::func]`, `#[ocaml::native_func]` or `#[ocaml::bytecode_func]`
+//!   are declared using `external`, functions with more than 5 arguments use both the bytecode
+//!   and native symbols
+//! - A `#[ocaml::bytecode_func]` named `f_bytecode` paired with a `#[no_mangle] extern "C" fn f` is
+//!   declared using both symbols, it's also declared as `[@@noalloc]` with unboxed/untagged
+//!   arguments when every argument and the return type is a float, an integer, a bool or unit
+//! - `ocaml::Value` is declared as `Obj.t`
+//! - Types deriving `IntoValue`, `FromValue` or `TryFromValue` produce a type definition matching
+//!   the derived representation
+//!
+//! The inferred OCaml type can be replaced using `#[ocaml::sig("int -> int")]` on functions and
+//! `#[ocaml(sig = "int * int")]` on types.
+//!
+//! ```rust,no_run
+//! // in the `main` function of build.rs
+//! ocaml_build::Sigs::new("src/stubs.ml").generate().unwrap();
+//! ```
+//!
+//! The `.mli` file declares functions using `val`, except `[@@noalloc]` functions which have to
+//! stay `external` to keep their unboxed calling convention.
+//!
+//! Build scripts run before the crate is compiled, so the declarations come from parsing the
+//! source files rather than from the expanded crate, which has some limits:
+//!
+//! - Functions or types generated by macros are missing, a warning is emitted when a
+//!   `macro_rules!` definition contains `#[ocaml::func]`
+//! - `#[cfg]` attributes are not evaluated, gated items are declared for every configuration
+//!   with a warning
+//! - Only files in the source directory are read, re-exports of functions defined in other
+//!   crates are missing
+//!
+//! Missing declarations can be written by hand in a separate OCaml module.
+//!
+//! When the OCaml types are written by hand, `LayoutCheck` compares them with the Rust types
+//! instead, failing the build when the constructor order, arity or float record status differ:
+//!
+//! ```rust,no_run
+//! // in the `main` function of build.rs
+//! ocaml_build::LayoutCheck::new("src/types.mli").check().unwrap();
+//! ```
+
+use std::path::{Path, PathBuf};
+
+mod layout;
+mod mli;
+mod stubs;
+mod types;
+
+#[cfg(test)]
+mod tests;
+
+/// Generates `.ml` and `.mli` files declaring the functions and types exported from a crate
+pub struct Sigs {
+    output: PathBuf,
+    source_dir: PathBuf,
+}
+
+fn source_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    let mut entries = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            source_files(&path, files)?;
+        } else if path.extension().and_then(|ext| ext.to_str()) == Some("rs") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Parse all the Rust source files in `dir`, the build script is re-run when they change
+fn parse_sources(dir: &Path) -> std::io::Result<Vec<syn::File>> {
+    let mut paths = Vec::new();
+    source_files(dir, &mut paths)?;
+    paths
+        .iter()
+        .map(|path| {
+            println!("cargo:rerun-if-changed={}", path.display());
+            let source = std::fs::read_to_string(path)?;
+            syn::parse_file(&source).map_err(|e| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("{}: {}", path.display(), e),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Write `contents` to `path` if it is different from the existing file, to avoid triggering
+/// rebuilds of the OCaml code
+fn write_if_changed(path: &Path, contents: &str) -> std::io::Result<()> {
+    if let Ok(existing) = std::fs::read_to_string(path) {
+        if existing == contents {
+            return Ok(());
+        }
+    }
+    std::fs::write(path, contents)
+}
+
+impl Sigs {
+    /// Generate stubs at `output`, the `.mli` file is written next to it
+    pub fn new<P: AsRef<Path>>(output: P) -> Sigs {
+        Sigs {
+            output: output.as_ref().to_path_buf(),
+            source_dir: PathBuf::from("src"),
+        }
+    }
+
+    /// Set the directory containing the Rust source files, defaults to `src`
+    pub fn with_source_dir<P: AsRef<Path>>(mut self, dir: P) -> Sigs {
+        self.source_dir = dir.as_ref().to_path_buf();
+        self
+    }
+
+    /// Generate the OCaml declarations for a single Rust source file
+    pub fn generate_from_str(source: &str) -> syn::Result<String> {
+        let mut stubs = stubs::Stubs::default();
+        stubs.add_file(&syn::parse_file(source)?);
+        Ok(stubs.render(false))
+    }
+
+    /// Read all the Rust source files and write the `.ml` and `.mli` files
+    pub fn generate(&self) -> std::io::Result<()> {
+        let mut stubs = stubs::Stubs::default();
+        for file in parse_sources(&self.source_dir)? {
+            stubs.add_file(&file);
+        }
+
+        for warning in &stubs.warnings {
+            println!("cargo:warning={}", warning);
+        }
+
+        write_if_changed(&self.output, &stubs.render(false))?;
+        write_if_changed(&self.output.with_extension("mli"), &stubs.render(true))
+    }
+}
+
+/// Checks that types deriving `IntoValue`, `FromValue` or `TryFromValue` have the same runtime
+/// representation as the OCaml type with the same name
+pub struct LayoutCheck {
+    interfaces: Vec<PathBuf>,
+    source_dir: PathBuf,
+}
+
+impl LayoutCheck {
+    /// Check the type definitions in the OCaml file `interface`
+    pub fn new<P: AsRef<Path>>(interface: P) -> LayoutCheck {
+        LayoutCheck {
+            interfaces: vec![interface.as_ref().to_path_buf()],
+            source_dir: PathBuf::from("src"),
+        }
+    }
+
+    /// Also check the type definitions in `interface`
+    pub fn with_interface<P: AsRef<Path>>(mut self, interface: P) -> LayoutCheck {
+        self.interfaces.push(interface.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the directory containing the Rust source files, defaults to `src`
+    pub fn with_source_dir<P: AsRef<Path>>(mut self, dir: P) -> LayoutCheck {
+        self.source_dir = dir.as_ref().to_path_buf();
+        self
+    }
+
+    /// Compare the Rust types from a single source file with OCaml type definitions, returning a
+    /// description of each mismatch
+    pub fn check_str(rust: &str, ocaml: &str) -> syn::Result<Vec<String>> {
+        Ok(layout::check(&[syn::parse_file(rust)?], ocaml))
+    }
+
+    /// Compare the Rust types with the OCaml type definitions, an `InvalidData` error listing
+    /// every mismatch is returned when they don't match
+    pub fn check(&self) -> std::io::Result<()> {
+        let files = parse_sources(&self.source_dir)?;
+        let mut errors = Vec::new();
+        for path in &self.interfaces {
+            println!("cargo:rerun-if-changed={}", path.display());
+            let source = std::fs::read_to_string(path)?;
+            errors.extend(
+                layout::check(&files, &source)
+                    .into_iter()
+                    .map(|e| format!("{}: {}", path.display(), e)),
+            );
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                errors.join("\n"),
+            ))
+        }
+    }
+}
@@ -0,0 +1,276 @@
+/// Representation of an OCaml type definition, only the parts that affect the runtime layout
+#[derive(Debug, PartialEq)]
+pub(crate) enum TypeDef {
+    /// Record with its field names, `floats` is set when every field is a float
+    Record { fields: Vec<String>, floats: bool },
+
+    /// Variant type with the name and arity of each constructor
+    Variant(Vec<(String, usize)>),
+
+    /// Polymorphic variant type with the name and arity of each constructor, the arity of
+    /// constructors with a tuple argument is the size of the tuple
+    Polymorphic(Vec<(String, usize)>),
+
+    /// Tuple of the given size
+    Tuple(usize),
+
+    /// Abstract types and type abbreviations
+    Other,
+}
+
+/// Keywords that end a type definition
+const ITEMS: &[&str] = &[
+    "type",
+    "and",
+    "val",
+    "external",
+    "module",
+    "exception",
+    "open",
+    "include",
+    "class",
+    "end",
+    "let",
+];
+
+fn tokenize(source: &str) -> Vec<String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    // Depth of `[@...]` attributes, which are skipped
+    let mut attr = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '(' && next == Some('*') {
+            let mut depth = 0;
+            while i < chars.len() {
+                if chars[i] == '(' && chars.get(i + 1) == Some(&'*') {
+                    depth += 1;
+                    i += 2;
+                } else if chars[i] == '*' && chars.get(i + 1) == Some(&')') {
+                    depth -= 1;
+                    i += 2;
+                    if depth == 0 {
+                        break;
+                    }
+                } else {
+                    i += 1;
+                }
+            }
+        } else if c == '"' {
+            i += 1;
+            while i < chars.len() && chars[i] != '"' {
+                i += if chars[i] == '\\' { 2 } else { 1 };
+            }
+            i += 1;
+            if attr == 0 {
+                tokens.push("\"\"".to_string());
+            }
+        } else if c.is_alphanumeric() || c == '_' || c == '\'' {
+            let start = i;
+            i += 1;
+            while i < chars.len()
+                && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '\'')
+            {
+                i += 1;
+            }
+            // Module paths such as `Float.t` are kept as a single token
+            while i + 1 < chars.len() && chars[i] == '.' && chars[i + 1].is_alphabetic() {
+                i += 1;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+            }
+            if attr == 0 {
+                tokens.push(chars[start..i].iter().collect());
+            }
+        } else if c == '[' && next == Some('@') {
+            attr += 1;
+            i += 2;
+        } else {
+            if attr > 0 {
+                match c {
+                    '[' => attr += 1,
+                    ']' => attr -= 1,
+                    _ => (),
+                }
+            } else if c == '-' && next == Some('>') {
+                tokens.push("->".to_string());
+                i += 1;
+            } else {
+                tokens.push(c.to_string());
+            }
+            i += 1;
+        }
+    }
+    tokens
+}
+
+/// Split `tokens` on `sep` when it isn't nested in parentheses or brackets
+fn split<'a>(tokens: &'a [String], sep: &str) -> Vec<&'a [String]> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, t) in tokens.iter().enumerate() {
+        match t.as_str() {
+            "(" | "[" | "{" => depth += 1,
+            ")" | "]" | "}" => depth -= 1,
+            s if s == sep && depth == 0 => {
+                parts.push(&tokens[start..i]);
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    parts.push(&tokens[start..]);
+    parts
+}
+
+/// Number of values stored for a constructor argument: `a * b` has two, `(a * b)` has one and
+/// inline records have one per field
+fn arity(tokens: &[String]) -> usize {
+    match tokens.first().map(String::as_str) {
+        None => 0,
+        Some("{") => record_fields(tokens).len(),
+        Some(_) => split(tokens, "*").len(),
+    }
+}
+
+/// Field names and types of a record definition `{ a: t; mutable b: u }`
+fn record_fields(tokens: &[String]) -> Vec<(String, &[String])> {
+    let inner = &tokens[1..tokens.len().saturating_sub(1)];
+    split(inner, ";")
+        .into_iter()
+        .filter(|field| !field.is_empty())
+        .filter_map(|field| {
+            let field = match field.first().map(String::as_str) {
+                Some("mutable") => &field[1..],
+                _ => field,
+            };
+            match field {
+                [name, colon, ty @ ..] if colon == ":" => Some((name.clone(), ty)),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+fn constructors(tokens: &[String]) -> Vec<(String, usize)> {
+    split(tokens, "|")
+        .into_iter()
+        .filter(|c| !c.is_empty())
+        .map(|c| {
+            let (name, rest) = match c {
+                [tick, name, rest @ ..] if tick == "`" => (name.clone(), rest),
+                [name, rest @ ..] => (name.clone(), rest),
+                [] => unreachable!(),
+            };
+            let arity = match rest {
+                [of, args @ ..] if of == "of" => arity(args),
+                _ => 0,
+            };
+            (name, arity)
+        })
+        .collect()
+}
+
+fn is_constructor(s: &str) -> bool {
+    matches!(s.chars().next(), Some(c) if c.is_uppercase()) && !s.contains('.')
+}
+
+fn type_def(body: &[String]) -> TypeDef {
+    // Skip manifest types in `type t = M.t = A | B` and private definitions
+    let mut body = body;
+    if let Some(i) = body.iter().position(|t| t == "=") {
+        body = &body[i + 1..];
+    }
+    if body.first().map(String::as_str) == Some("private") {
+        body = &body[1..];
+    }
+
+    match body.first().map(String::as_str) {
+        Some("{") => {
+            let fields = record_fields(body);
+            let floats = !fields.is_empty()
+                && fields
+                    .iter()
+                    .all(|(_, ty)| matches!(ty, [t] if t == "float" || t == "Float.t"));
+            TypeDef::Record {
+                fields: fields.into_iter().map(|(name, _)| name).collect(),
+                floats,
+            }
+        }
+        Some("[") => {
+            let inner = &body[1..body.len().saturating_sub(1)];
+            let inner = match inner.first().map(String::as_str) {
+                Some(">") | Some("<") => &inner[1..],
+                _ => inner,
+            };
+            TypeDef::Polymorphic(constructors(inner))
+        }
+        Some("|") => TypeDef::Variant(constructors(body)),
+        Some(c) if is_constructor(c) => TypeDef::Variant(constructors(body)),
+        Some(_) if split(body, "->").len() == 1 && split(body, "*").len() > 1 => {
+            TypeDef::Tuple(split(body, "*").len())
+        }
+        _ => TypeDef::Other,
+    }
+}
+
+/// Parse the type definitions from an OCaml interface or implementation
+pub(crate) fn parse(source: &str) -> Vec<(String, TypeDef)> {
+    let tokens = tokenize(source);
+    let mut defs = Vec::new();
+    let mut i = 0;
+    // Set when `and` continues a group of type definitions
+    let mut in_types = false;
+    while i < tokens.len() {
+        let is_type = tokens[i] == "type" && (i == 0 || tokens[i - 1] != "module");
+        let is_and = tokens[i] == "and" && in_types;
+        if !is_type && !is_and {
+            in_types = false;
+            i += 1;
+            continue;
+        }
+        i += 1;
+        if tokens.get(i).map(String::as_str) == Some("nonrec") {
+            i += 1;
+        }
+
+        // Type parameters
+        if tokens.get(i).map(String::as_str) == Some("(") {
+            while i < tokens.len() && tokens[i] != ")" {
+                i += 1;
+            }
+            i += 1;
+        } else {
+            if matches!(tokens.get(i).map(String::as_str), Some("+") | Some("-")) {
+                i += 1;
+            }
+            if matches!(tokens.get(i), Some(t) if t.starts_with('\'')) {
+                i += 1;
+            }
+        }
+
+        let name = match tokens.get(i) {
+            Some(name) => name.clone(),
+            None => break,
+        };
+        i += 1;
+
+        let start = i;
+        while i < tokens.len() && !ITEMS.contains(&tokens[i].as_str()) {
+            i += 1;
+        }
+        let def = match tokens[start..i].split_first() {
+            Some((eq, body)) if eq == "=" => type_def(body),
+            _ => TypeDef::Other,
+        };
+        defs.push((name, def));
+        in_types = true;
+    }
+    defs
+}
@@ -0,0 +1,532 @@
+use crate::types::{apply, snake_case, type_var, Context, Type};
+
+/// How a function is exported to OCaml
+#[derive(Clone, Copy, PartialEq)]
+enum Export {
+    /// `#[ocaml::func]`
+    Func,
+
+    /// `#[ocaml::native_func]`
+    Native,
+
+    /// `#[ocaml::bytecode_func]`
+    Bytecode,
+
+    /// `#[no_mangle] extern "C"` functions, used as the native half of unboxed functions
+    Plain,
+}
+
+struct Function {
+    export: Export,
+    sig: syn::Signature,
+    ocaml_sig: Option<String>,
+}
+
+/// OCaml declarations collected from Rust source files
+#[derive(Default)]
+pub(crate) struct Stubs {
+    types: Vec<String>,
+    functions: Vec<Function>,
+    pub(crate) warnings: Vec<String>,
+}
+
+/// Whether an item is only compiled for some configurations
+fn has_cfg(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| attr.path.is_ident("cfg"))
+}
+
+/// Whether the tokens of a `macro_rules!` definition contain an attribute exporting a function
+fn exports_functions(mac: &syn::ItemMacro) -> bool {
+    if !mac.mac.path.is_ident("macro_rules") {
+        return false;
+    }
+    let tokens = mac.mac.tokens.to_string();
+    ["func", "native_func", "bytecode_func"]
+        .iter()
+        .any(|name| tokens.contains(&format!("ocaml :: {}", name)))
+}
+
+/// Check if `path` is `ocaml::name` or `name`
+fn is_ocaml_attr(path: &syn::Path, name: &str) -> bool {
+    let segments: Vec<_> = path.segments.iter().map(|s| s.ident.to_string()).collect();
+    match segments.as_slice() {
+        [a] => a == name,
+        [ocaml, a] => ocaml == "ocaml" && a == name,
+        _ => false,
+    }
+}
+
+pub(crate) fn is_derived(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().filter(|attr| attr.path.is_ident("derive")).any(|attr| {
+        match attr.parse_meta() {
+            Ok(syn::Meta::List(list)) => list.nested.iter().any(|nested| match nested {
+                syn::NestedMeta::Meta(meta) => matches!(
+                    meta.path().segments.last(),
+                    Some(s) if s.ident == "IntoValue" || s.ident == "FromValue" || s.ident == "TryFromValue"
+                ),
+                _ => false,
+            }),
+            _ => false,
+        }
+    })
+}
+
+/// Parameters of `#[ocaml(...)]` attributes, path parameters have an empty value
+pub(crate) fn ocaml_params(attrs: &[syn::Attribute]) -> Vec<(String, String)> {
+    let mut params = Vec::new();
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("ocaml")) {
+        if let Ok(syn::Meta::List(list)) = attr.parse_meta() {
+            for nested in list.nested.iter() {
+                match nested {
+                    syn::NestedMeta::Meta(syn::Meta::Path(path)) => {
+                        if let Some(ident) = path.get_ident() {
+                            params.push((ident.to_string(), String::new()))
+                        }
+                    }
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) => {
+                        if let Some(ident) = nv.path.get_ident() {
+                            let value = match &nv.lit {
+                                syn::Lit::Str(s) => s.value(),
+                                syn::Lit::Int(i) => i.base10_digits().to_string(),
+                                _ => String::new(),
+                            };
+                            params.push((ident.to_string(), value))
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+    params
+}
+
+pub(crate) fn ocaml_param(params: &[(String, String)], name: &str) -> Option<String> {
+    params
+        .iter()
+        .find(|(k, _)| k == name)
+        .map(|(_, v)| v.clone())
+}
+
+/// The tag set using `#[ocaml(tag = N)]`
+fn tag_attr(variant: &syn::Variant) -> Option<usize> {
+    let params = ocaml_params(&variant.attrs);
+    ocaml_param(&params, "tag").and_then(|tag| tag.parse().ok())
+}
+
+/// The explicit discriminant of a variant
+fn discriminant(variant: &syn::Variant) -> Option<usize> {
+    match &variant.discriminant {
+        Some((
+            _,
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Int(i),
+                ..
+            }),
+        )) => i.base10_parse().ok(),
+        _ => None,
+    }
+}
+
+/// Whether the tags of an enum are its discriminants, set using `#[ocaml(discriminants)]`
+fn uses_discriminants(e: &syn::ItemEnum) -> bool {
+    ocaml_param(&ocaml_params(&e.attrs), "discriminants").is_some()
+}
+
+/// Whether each variant is a constructor with arguments along with its tag, using the same
+/// numbering as the derive macros
+pub(crate) fn variant_tags(e: &syn::ItemEnum) -> Vec<(bool, usize)> {
+    let discriminants = uses_discriminants(e);
+    let mut next = [0, 0];
+    let mut next_discriminant = 0;
+    e.variants
+        .iter()
+        .map(|variant| {
+            let is_block = !stored_fields(&variant.fields).is_empty();
+            let tag = if discriminants {
+                let d = discriminant(variant).unwrap_or(next_discriminant);
+                next_discriminant = d + 1;
+                tag_attr(variant).unwrap_or(d)
+            } else {
+                let tag = tag_attr(variant).unwrap_or(next[is_block as usize]);
+                next[is_block as usize] = tag + 1;
+                tag
+            };
+            (is_block, tag)
+        })
+        .collect()
+}
+
+pub(crate) fn has_explicit_tags(e: &syn::ItemEnum) -> bool {
+    uses_discriminants(e) || e.variants.iter().any(|v| tag_attr(v).is_some())
+}
+
+fn is_phantom(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(p) => {
+            matches!(p.path.segments.last(), Some(segment) if segment.ident == "PhantomData")
+        }
+        _ => false,
+    }
+}
+
+/// Fields stored in the OCaml value in the order they are stored, following the `skip` and `index`
+/// field attributes used by the derive macros. `PhantomData` fields are skipped unless indexed
+pub(crate) fn stored_fields(fields: &syn::Fields) -> Vec<&syn::Field> {
+    let mut next = 0;
+    let mut slots: Vec<(usize, &syn::Field)> = Vec::new();
+    for field in fields.iter() {
+        let params = ocaml_params(&field.attrs);
+        let index = ocaml_param(&params, "index");
+        if ocaml_param(&params, "skip").is_some() || (is_phantom(&field.ty) && index.is_none()) {
+            continue;
+        }
+        let index = match index.and_then(|i| i.parse().ok()) {
+            Some(index) => index,
+            None => {
+                while slots.iter().any(|(i, _)| *i == next) {
+                    next += 1;
+                }
+                next
+            }
+        };
+        next = index + 1;
+        slots.push((index, field));
+    }
+    slots.sort_by_key(|(i, _)| *i);
+    slots.into_iter().map(|(_, field)| field).collect()
+}
+
+/// Constructor arguments or record fields
+fn fields_def(ctx: &mut Context, fields: &[&syn::Field]) -> String {
+    if fields.iter().all(|field| field.ident.is_some()) {
+        let fields: Vec<_> = fields
+            .iter()
+            .map(|field| {
+                let name = field.ident.as_ref().unwrap().to_string();
+                let name = name.trim_start_matches("r#");
+                format!("{}: {}", name, ctx.ocaml_type(&field.ty).name)
+            })
+            .collect();
+        format!("{{ {} }}", fields.join("; "))
+    } else {
+        let fields: Vec<_> = fields
+            .iter()
+            .map(|field| ctx.ocaml_type(&field.ty).atom())
+            .collect();
+        fields.join(" * ")
+    }
+}
+
+fn struct_def(
+    ctx: &mut Context,
+    name: &str,
+    params: &[(String, String)],
+    s: &syn::ItemStruct,
+) -> String {
+    let fields = stored_fields(&s.fields);
+    if ocaml_param(params, "unboxed").is_some() && fields.len() == 1 {
+        return ctx.ocaml_type(&fields[0].ty).name;
+    }
+    match (&s.fields, fields.len()) {
+        (_, 0) => "unit".to_string(),
+        (syn::Fields::Named(_), _) => fields_def(ctx, &fields),
+        // A single unnamed field is stored in a block of size 1, which matches a constructor
+        (_, 1) => format!("{} of {}", to_constructor(name), fields_def(ctx, &fields)),
+        _ => fields_def(ctx, &fields),
+    }
+}
+
+fn to_constructor(name: &str) -> String {
+    let mut s = snake_case(name);
+    if let Some(c) = s.get_mut(0..1) {
+        c.make_ascii_uppercase();
+    }
+    s
+}
+
+/// Orders the constructors of an enum with explicit tags by tag, gaps are filled with placeholder
+/// constructors so that every constructor has the same tag in OCaml and Rust
+fn tagged_constructors(e: &syn::ItemEnum, variants: Vec<String>) -> Vec<String> {
+    let mut constants: Vec<Option<String>> = Vec::new();
+    let mut blocks: Vec<Option<String>> = Vec::new();
+    for ((is_block, tag), variant) in variant_tags(e).into_iter().zip(variants) {
+        let slots = if is_block {
+            &mut blocks
+        } else {
+            &mut constants
+        };
+        if slots.len() <= tag {
+            slots.resize(tag + 1, None);
+        }
+        slots[tag] = Some(variant);
+    }
+    let constants = constants
+        .into_iter()
+        .enumerate()
+        .map(|(i, c)| c.unwrap_or_else(|| format!("Unused_{}", i)));
+    let blocks = blocks
+        .into_iter()
+        .enumerate()
+        .map(|(i, c)| c.unwrap_or_else(|| format!("Unused_block_{} of unit", i)));
+    constants.chain(blocks).collect()
+}
+
+fn enum_def(ctx: &mut Context, params: &[(String, String)], e: &syn::ItemEnum) -> String {
+    let polymorphic = ocaml_param(params, "polymorphic").is_some();
+    let variants: Vec<_> = e
+        .variants
+        .iter()
+        .map(|variant| {
+            let fields = stored_fields(&variant.fields);
+            let name = if polymorphic {
+                format!("`{}", variant.ident)
+            } else {
+                variant.ident.to_string()
+            };
+            if fields.is_empty() {
+                name
+            } else {
+                format!("{} of {}", name, fields_def(ctx, &fields))
+            }
+        })
+        .collect();
+    if polymorphic {
+        format!("[ {} ]", variants.join(" | "))
+    } else if has_explicit_tags(e) {
+        format!("| {}", tagged_constructors(e, variants).join(" | "))
+    } else {
+        format!("| {}", variants.join(" | "))
+    }
+}
+
+fn type_def(name: &syn::Ident, generics: &syn::Generics, body: String) -> String {
+    let params: Vec<_> = generics
+        .type_params()
+        .map(|param| Type {
+            name: type_var(&param.ident.to_string()),
+            tuple: false,
+        })
+        .collect();
+    format!(
+        "{} = {}",
+        apply(&params, &snake_case(&name.to_string())),
+        body
+    )
+}
+
+impl Stubs {
+    /// Collect functions and types from a Rust source file, including inline modules
+    pub(crate) fn add_file(&mut self, file: &syn::File) {
+        self.add_items(&file.items)
+    }
+
+    fn add_items(&mut self, items: &[syn::Item]) {
+        for item in items {
+            match item {
+                syn::Item::Fn(f) => self.add_fn(f),
+                syn::Item::Struct(s) if is_derived(&s.attrs) => {
+                    let params = ocaml_params(&s.attrs);
+                    let body = ocaml_param(&params, "sig").unwrap_or_else(|| {
+                        let mut ctx = Context::new(&s.generics);
+                        struct_def(&mut ctx, &s.ident.to_string(), &params, s)
+                    });
+                    self.warn_cfg(&s.attrs, "type", &s.ident);
+                    self.types.push(type_def(&s.ident, &s.generics, body));
+                }
+                syn::Item::Enum(e) if is_derived(&e.attrs) => {
+                    let params = ocaml_params(&e.attrs);
+                    let body = ocaml_param(&params, "sig").unwrap_or_else(|| {
+                        let mut ctx = Context::new(&e.generics);
+                        enum_def(&mut ctx, &params, e)
+                    });
+                    self.warn_cfg(&e.attrs, "type", &e.ident);
+                    self.types.push(type_def(&e.ident, &e.generics, body));
+                }
+                syn::Item::Mod(m) => {
+                    if let Some((_, items)) = &m.content {
+                        self.add_items(items)
+                    }
+                }
+                syn::Item::Macro(m) if exports_functions(m) => {
+                    let name = m.ident.as_ref().map(|i| i.to_string()).unwrap_or_default();
+                    self.warnings.push(format!(
+                        "functions exported by the macro `{}` are not declared",
+                        name
+                    ));
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn add_fn(&mut self, f: &syn::ItemFn) {
+        let mut export = None;
+        let mut ocaml_sig = None;
+        for attr in &f.attrs {
+            if is_ocaml_attr(&attr.path, "func") {
+                export = Some(Export::Func);
+            } else if is_ocaml_attr(&attr.path, "native_func") {
+                export = Some(Export::Native);
+            } else if is_ocaml_attr(&attr.path, "bytecode_func") {
+                export = Some(Export::Bytecode);
+            } else if is_ocaml_attr(&attr.path, "sig") {
+                ocaml_sig = attr.parse_args::<syn::LitStr>().ok().map(|s| s.value());
+            } else if attr.path.is_ident("no_mangle") && f.sig.abi.is_some() && export.is_none() {
+                export = Some(Export::Plain);
+            }
+        }
+
+        if let Some(export) = export {
+            if export != Export::Plain {
+                self.warn_cfg(&f.attrs, "function", &f.sig.ident);
+            }
+            self.functions.push(Function {
+                export,
+                sig: f.sig.clone(),
+                ocaml_sig,
+            })
+        }
+    }
+
+    /// Items are declared regardless of `#[cfg]`, which can refer to symbols that don't exist
+    fn warn_cfg(&mut self, attrs: &[syn::Attribute], kind: &str, ident: &syn::Ident) {
+        if has_cfg(attrs) {
+            self.warnings.push(format!(
+                "{} `{}` is declared for every configuration, ignoring its #[cfg]",
+                kind, ident
+            ));
+        }
+    }
+
+    fn find(&self, name: &str, export: Export) -> Option<&Function> {
+        self.functions
+            .iter()
+            .find(|f| f.export == export && f.sig.ident == name)
+    }
+
+    /// Render the `.ml` file when `interface` is false, otherwise the `.mli` file, which uses
+    /// `val` for functions that don't need to be declared as `external` in the interface
+    pub(crate) fn render(&self, interface: bool) -> String {
+        let mut out = String::from("(* Generated by ocaml-build, do not edit *)\n");
+
+        for (i, def) in self.types.iter().enumerate() {
+            let keyword = if i == 0 { "\ntype" } else { "and" };
+            out.push_str(&format!("{} {}\n", keyword, def));
+        }
+
+        if !self.functions.is_empty() {
+            out.push('\n');
+        }
+
+        for f in &self.functions {
+            let name = f.sig.ident.to_string();
+            let external = match f.export {
+                Export::Plain => continue,
+                Export::Func if f.sig.inputs.len() > 5 => {
+                    let symbols = format!("\"{}_bytecode\" \"{}\"", name, name);
+                    external(&name, f, &symbols, false, interface)
+                }
+                Export::Func | Export::Native => {
+                    external(&name, f, &format!("\"{}\"", name), false, interface)
+                }
+                Export::Bytecode => match name.strip_suffix("_bytecode") {
+                    // Functions with more than 5 arguments already declare both symbols
+                    Some(native) if self.find(native, Export::Func).is_some() => continue,
+                    Some(native) => match self.find(native, Export::Plain) {
+                        Some(n) => {
+                            let symbols = format!("\"{}\" \"{}\"", name, native);
+                            let f = Function {
+                                export: Export::Plain,
+                                sig: n.sig.clone(),
+                                ocaml_sig: n.ocaml_sig.clone().or_else(|| f.ocaml_sig.clone()),
+                            };
+                            external(native, &f, &symbols, true, interface)
+                        }
+                        None => external(&name, f, &format!("\"{}\"", name), false, interface),
+                    },
+                    None => external(&name, f, &format!("\"{}\"", name), false, interface),
+                },
+            };
+            out.push_str(&external);
+            out.push('\n');
+        }
+
+        out
+    }
+}
+
+/// Arguments and return type of a Rust function as OCaml types
+fn signature(sig: &syn::Signature) -> (Vec<Type>, Type) {
+    let mut ctx = Context::new(&sig.generics);
+    let args = sig
+        .inputs
+        .iter()
+        .filter_map(|arg| match arg {
+            syn::FnArg::Typed(t) => Some(ctx.ocaml_type(&t.ty)),
+            syn::FnArg::Receiver(_) => None,
+        })
+        .collect();
+    let ret = match &sig.output {
+        syn::ReturnType::Default => Type {
+            name: "unit".to_string(),
+            tuple: false,
+        },
+        syn::ReturnType::Type(_, t) => ctx.ocaml_type(t),
+    };
+    (args, ret)
+}
+
+/// Annotate an argument of an unboxed function with the attribute used to pass it unboxed
+fn unboxed(t: &Type) -> String {
+    match t.name.as_str() {
+        "float" | "int32" | "int64" | "nativeint" => format!("({} [@unboxed])", t.name),
+        "int" => "(int [@untagged])".to_string(),
+        _ => t.atom(),
+    }
+}
+
+/// `[@@noalloc]` functions are also declared with `external` in interfaces, so that callers
+/// don't go through a closure and keep the unboxed calling convention
+fn external(name: &str, f: &Function, symbols: &str, noalloc: bool, interface: bool) -> String {
+    let (args, ret) = signature(&f.sig);
+    // Functions taking or returning boxed values may allocate or raise, so they can't be noalloc
+    let noalloc = noalloc
+        && args.iter().chain(Some(&ret)).all(|t| {
+            matches!(
+                t.name.as_str(),
+                "float" | "int" | "int32" | "int64" | "nativeint" | "bool" | "unit"
+            )
+        });
+    let all_floats = args.iter().chain(Some(&ret)).all(|t| t.name == "float");
+    let mut attrs = "";
+    let ocaml_sig = match &f.ocaml_sig {
+        Some(s) => s.clone(),
+        None => {
+            let (mut args, ret) = if noalloc && !all_floats {
+                (args.iter().map(unboxed).collect(), unboxed(&ret))
+            } else {
+                (
+                    args.iter().map(|t| t.name.clone()).collect::<Vec<_>>(),
+                    ret.name,
+                )
+            };
+            if args.is_empty() {
+                args.push("unit".to_string())
+            }
+            args.push(ret);
+            args.join(" -> ")
+        }
+    };
+    if noalloc {
+        attrs = if all_floats {
+            " [@@unboxed] [@@noalloc]"
+        } else {
+            " [@@noalloc]"
+        };
+    }
+    if interface && !noalloc {
+        return format!("val {}: {}", name, ocaml_sig);
+    }
+    format!("external {}: {} = {}{}", name, ocaml_sig, symbols, attrs)
+}
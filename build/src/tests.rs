@@ -0,0 +1,398 @@
+use crate::{LayoutCheck, Sigs};
+
+fn generate(source: &str) -> Vec<String> {
+    Sigs::generate_from_str(source)
+        .unwrap()
+        .lines()
+        .skip(1)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+#[test]
+fn test_externals() {
+    let lines = generate(
+        r#"
+        #[ocaml::func]
+        pub fn incr_example(mut e: Example<'static>) -> Example<'static> { e }
+
+        #[ocaml::func]
+        pub fn build_tuple(i: ocaml::Int) -> (ocaml::Int, ocaml::Int, ocaml::Int) { todo!() }
+
+        #[ocaml::func]
+        pub fn average(arr: ocaml::Array<f64>) -> Result<f64, ocaml::Error> { todo!() }
+
+        #[ocaml::func]
+        pub fn nothing() {}
+
+        #[ocaml::func]
+        pub fn many(a: f64, b: f64, c: f64, d: f64, e: f64, f: Option<Vec<(i32, String)>>) {}
+
+        #[ocaml::func]
+        pub fn apply(f: ocaml::Fn2<(i64, f64), String, Option<String>>, x: ocaml::Int) {}
+
+        #[ocaml::func]
+        pub fn flatten(a: ocaml::bigarray::ArrayN<f32>) -> ocaml::bigarray::Array1<f32> { todo!() }
+
+        #[ocaml::func]
+        pub fn fft(a: Array1<Complex<f64>>, b: Array1<CamlInt>) -> Array1<isize> { todo!() }
+
+        #[ocaml::func]
+        pub fn scale(a: ocaml::FloatArray, b: Vec<f64>) -> Box<[f64]> { todo!() }
+
+        #[ocaml::sig("int -> int")]
+        #[ocaml::native_func]
+        pub unsafe fn incr(value: ocaml::Value) -> ocaml::Value { value }
+        "#,
+    );
+    assert_eq!(
+        lines,
+        vec![
+            "external incr_example: example -> example = \"incr_example\"",
+            "external build_tuple: int -> int * int * int = \"build_tuple\"",
+            "external average: float array -> float = \"average\"",
+            "external nothing: unit -> unit = \"nothing\"",
+            "external many: float -> float -> float -> float -> float -> (int32 * string) array option -> unit = \"many_bytecode\" \"many\"",
+            "external apply: ((int64 * float) -> string -> string option) -> int -> unit = \"apply\"",
+            "external flatten: (float, Bigarray.float32_elt, 'a) Bigarray.Genarray.t -> (float, Bigarray.float32_elt, Bigarray.c_layout) Bigarray.Array1.t = \"flatten\"",
+            "external fft: (Complex.t, Bigarray.complex64_elt, Bigarray.c_layout) Bigarray.Array1.t -> (int, Bigarray.int_elt, Bigarray.c_layout) Bigarray.Array1.t -> (nativeint, Bigarray.nativeint_elt, Bigarray.c_layout) Bigarray.Array1.t = \"fft\"",
+            "external scale: float array -> float array -> float array = \"scale\"",
+            "external incr: int -> int = \"incr\"",
+        ]
+    );
+}
+
+#[test]
+fn test_unboxed_externals() {
+    let lines = generate(
+        r#"
+        #[no_mangle]
+        pub extern "C" fn incrf(input: f64) -> f64 { input + 1.0 }
+
+        #[ocaml::bytecode_func]
+        pub fn incrf_bytecode(input: f64) -> f64 { incrf(input) }
+
+        #[no_mangle]
+        pub extern "C" fn scale(input: f64, n: isize) -> f64 { input * n as f64 }
+
+        #[ocaml::bytecode_func]
+        pub fn scale_bytecode(input: f64, n: isize) -> f64 { scale(input, n) }
+
+        #[no_mangle]
+        pub extern "C" fn make(input: ocaml::Value) -> ocaml::Value { input }
+
+        #[ocaml::bytecode_func]
+        pub fn make_bytecode(input: ocaml::Value) -> ocaml::Value { make(input) }
+        "#,
+    );
+    assert_eq!(
+        lines,
+        vec![
+            "external incrf: float -> float = \"incrf_bytecode\" \"incrf\" [@@unboxed] [@@noalloc]",
+            "external scale: (float [@unboxed]) -> (int [@untagged]) -> (float [@unboxed]) = \"scale_bytecode\" \"scale\" [@@noalloc]",
+            "external make: Obj.t -> Obj.t = \"make_bytecode\" \"make\"",
+        ]
+    );
+}
+
+#[test]
+fn test_types() {
+    let lines = generate(
+        r#"
+        #[derive(ocaml::IntoValue, ocaml::FromValue)]
+        struct Example<'a> {
+            name: &'a str,
+            i: ocaml::Int,
+        }
+
+        #[derive(IntoValue)]
+        enum Shape<T> {
+            Point,
+            Circle(T),
+            Rect { w: T, h: T },
+            Pair((i64, i64)),
+        }
+
+        #[derive(ocaml::IntoValue)]
+        #[ocaml(polymorphic)]
+        enum Polymorphic {
+            A,
+            B(ocaml::Int, String),
+        }
+
+        #[derive(ocaml::FromValue)]
+        struct Struct2 {
+            #[ocaml(index = 1)]
+            a: ocaml::Int,
+            #[ocaml(index = 0)]
+            b: String,
+            #[ocaml(skip)]
+            cache: Option<String>,
+            c: ocaml::Int,
+        }
+
+        #[derive(ocaml::FromValue)]
+        #[ocaml(unboxed)]
+        struct Wrapper(Vec<f64>);
+
+        #[derive(ocaml::FromValue)]
+        struct Single(f64);
+
+        #[derive(ocaml::FromValue)]
+        #[ocaml(sig = "int * int")]
+        struct Custom(ocaml::Value);
+
+        #[derive(ocaml::IntoValue)]
+        struct Tagged<T> {
+            id: ocaml::Int,
+            marker: std::marker::PhantomData<T>,
+        }
+
+        struct NotExported(ocaml::Int);
+        "#,
+    );
+    assert_eq!(
+        lines,
+        vec![
+            "type example = { name: string; i: int }",
+            "and 't shape = | Point | Circle of 't | Rect of { w: 't; h: 't } | Pair of (int64 * int64)",
+            "and polymorphic = [ `A | `B of int * string ]",
+            "and struct2 = { b: string; a: int; c: int }",
+            "and wrapper = float array",
+            "and single = Single of float",
+            "and custom = int * int",
+            "and 't tagged = { id: int }",
+        ]
+    );
+}
+
+#[test]
+fn test_interface() {
+    let mut stubs = crate::stubs::Stubs::default();
+    stubs.add_file(
+        &syn::parse_file(
+            r#"
+            #[derive(ocaml::IntoValue)]
+            struct Point { x: ocaml::Int, y: ocaml::Int }
+
+            #[ocaml::func]
+            pub fn origin() -> Point { todo!() }
+
+            #[no_mangle]
+            pub extern "C" fn incrf(input: f64) -> f64 { input + 1.0 }
+
+            #[ocaml::bytecode_func]
+            pub fn incrf_bytecode(input: f64) -> f64 { incrf(input) }
+            "#,
+        )
+        .unwrap(),
+    );
+    let lines: Vec<_> = stubs
+        .render(true)
+        .lines()
+        .skip(1)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect();
+    assert_eq!(
+        lines,
+        vec![
+            "type point = { x: int; y: int }",
+            "val origin: unit -> point",
+            "external incrf: float -> float = \"incrf_bytecode\" \"incrf\" [@@unboxed] [@@noalloc]",
+        ]
+    );
+}
+
+#[test]
+fn test_warnings() {
+    let mut stubs = crate::stubs::Stubs::default();
+    stubs.add_file(
+        &syn::parse_file(
+            r#"
+            macro_rules! getter {
+                ($name:ident) => {
+                    #[ocaml::func]
+                    pub fn $name() -> ocaml::Int { 0 }
+                };
+            }
+
+            #[cfg(feature = "extra")]
+            #[ocaml::func]
+            pub fn extra() {}
+
+            #[ocaml::func]
+            pub fn always() {}
+            "#,
+        )
+        .unwrap(),
+    );
+    assert_eq!(
+        stubs.warnings,
+        vec![
+            "functions exported by the macro `getter` are not declared",
+            "function `extra` is declared for every configuration, ignoring its #[cfg]",
+        ]
+    );
+}
+
+fn check(rust: &str, ocaml: &str) -> Vec<String> {
+    LayoutCheck::check_str(rust, ocaml).unwrap()
+}
+
+#[test]
+fn test_layout_check() {
+    let rust = r#"
+        #[derive(ocaml::IntoValue, ocaml::FromValue)]
+        struct Example<'a> {
+            name: &'a str,
+            i: ocaml::Int,
+        }
+
+        #[derive(ocaml::IntoValue)]
+        enum Shape {
+            Point,
+            Circle(f64),
+            Rect { w: f64, h: f64 },
+        }
+
+        #[derive(ocaml::IntoValue)]
+        #[ocaml(polymorphic)]
+        enum Polymorphic {
+            A,
+            B(ocaml::Int, String),
+        }
+
+        #[derive(ocaml::IntoValue)]
+        #[ocaml(floats_array)]
+        struct Vec2 {
+            x: f64,
+            y: f64,
+        }
+
+        #[derive(ocaml::IntoValue)]
+        struct Pair(ocaml::Int, ocaml::Int);
+    "#;
+
+    let ocaml = r#"
+        (* Matches the Rust definitions *)
+        type example = { name: string; mutable i: int }
+        and shape = Point | Circle of float | Rect of { w: float; h: float } [@@deriving show]
+        type polymorphic = [ `B of int * string | `A ]
+        type vec2 = { x: float; y: float }
+        type pair = int * int
+        val f: example -> unit
+    "#;
+    assert!(check(rust, ocaml).is_empty());
+
+    let ocaml = r#"
+        type example = { i: int; name: string }
+        type shape = Point | Rect of { w: float; h: float } | Circle of (float * float)
+        type polymorphic = [ `A | `B of (int * string) ]
+        type vec2 = { x: float; y: int }
+        type pair = int * int * int
+    "#;
+    assert_eq!(
+        check(rust, ocaml),
+        vec![
+            "type example: field 0 is i in OCaml but name in Rust",
+            "type example: field 1 is name in OCaml but i in Rust",
+            "type shape: constructor Circle has tag 1 in OCaml but 0 in Rust",
+            "type shape: constructor Rect has tag 0 in OCaml but 1 in Rust",
+            "type polymorphic: constructor B has 1 arguments in OCaml but 2 in Rust",
+            "type vec2: #[ocaml(floats_array)] is used but the OCaml record isn't a float record",
+            "type pair: OCaml tuple has 3 elements but Rust stores 2",
+        ]
+    );
+
+    let ocaml = "type vec2 = { x: float; y: float; z: float } type pair = Pair of int";
+    let rust = r#"
+        #[derive(ocaml::FromValue)]
+        struct Vec2 { x: f64, y: f64, z: f64 }
+
+        #[derive(ocaml::FromValue)]
+        struct Pair(ocaml::Int, ocaml::Int);
+    "#;
+    assert_eq!(
+        check(rust, ocaml),
+        vec![
+            "type vec2: OCaml stores this record as a float array, use #[ocaml(floats_array)]",
+            "type pair: OCaml type is a variant but Rust derives a different representation",
+        ]
+    );
+}
+
+#[test]
+fn test_tags() {
+    let rust = r#"
+        #[derive(ocaml::IntoValue)]
+        enum Status {
+            Ready,
+            #[ocaml(tag = 2)]
+            Failed(String),
+            #[ocaml(tag = 1)]
+            Done,
+        }
+    "#;
+    assert_eq!(
+        generate(rust),
+        vec!["type status = | Ready | Done | Unused_block_0 of unit | Unused_block_1 of unit | Failed of string"]
+    );
+
+    let ocaml = "type status = Ready | Done | A of int | B of int | Failed of string";
+    assert!(check(rust, ocaml).is_empty());
+
+    let ocaml = "type status = Ready | Done | Failed of string";
+    assert_eq!(
+        check(rust, ocaml),
+        vec!["type status: constructor Failed has tag 0 in OCaml but 2 in Rust"]
+    );
+}
+
+#[test]
+fn test_discriminants() {
+    // Discriminants are only used as tags with `#[ocaml(discriminants)]`
+    let rust = r#"
+        #[derive(ocaml::IntoValue)]
+        enum Level {
+            Low = 1,
+            High = 3,
+        }
+    "#;
+    assert_eq!(generate(rust), vec!["type level = | Low | High"]);
+
+    // Variants without a discriminant follow the previous variant of either kind, like in Rust
+    let rust = r#"
+        #[derive(ocaml::IntoValue)]
+        #[ocaml(discriminants)]
+        #[repr(u8)]
+        enum Mixed {
+            A = 1,
+            B(ocaml::Int),
+            C = 4,
+            D(String),
+            #[ocaml(tag = 0)]
+            E,
+        }
+    "#;
+    assert_eq!(
+        generate(rust),
+        vec!["type mixed = | E | A | Unused_2 | Unused_3 | C | Unused_block_0 of unit | Unused_block_1 of unit | B of int | Unused_block_3 of unit | Unused_block_4 of unit | D of string"]
+    );
+
+    let ocaml = "type mixed = E | A | Unused_2 | Unused_3 | C | X of int | Y of int | B of int | Z of int | W of int | D of string";
+    assert!(check(rust, ocaml).is_empty());
+
+    let ocaml = "type mixed = A | B of int | C | D of string | E";
+    assert_eq!(
+        check(rust, ocaml),
+        vec![
+            "type mixed: constructor A has tag 0 in OCaml but 1 in Rust",
+            "type mixed: constructor B has tag 0 in OCaml but 2 in Rust",
+            "type mixed: constructor C has tag 1 in OCaml but 4 in Rust",
+            "type mixed: constructor D has tag 1 in OCaml but 5 in Rust",
+            "type mixed: constructor E has tag 2 in OCaml but 0 in Rust",
+        ]
+    );
+}
@@ -0,0 +1,256 @@
+/// An OCaml type expression, `tuple` is set when the type needs parentheses to be used as an
+/// argument of a type constructor or tuple
+pub(crate) struct Type {
+    pub(crate) name: String,
+    pub(crate) tuple: bool,
+}
+
+impl Type {
+    fn new(name: impl Into<String>) -> Type {
+        Type {
+            name: name.into(),
+            tuple: false,
+        }
+    }
+
+    /// The type in a position where tuples must be parenthesized
+    pub(crate) fn atom(&self) -> String {
+        if self.tuple {
+            format!("({})", self.name)
+        } else {
+            self.name.clone()
+        }
+    }
+}
+
+/// Converts Rust types into OCaml types, `generics` contains the type parameters in scope and
+/// types that can't be inferred, like the layout of an `ArrayN`, are assigned fresh type variables
+#[derive(Default)]
+pub(crate) struct Context {
+    pub(crate) generics: Vec<String>,
+    vars: usize,
+}
+
+/// Convert a Rust type name to an OCaml type name, `MyType` becomes `my_type`
+pub(crate) fn snake_case(s: &str) -> String {
+    let mut dest = String::new();
+    let mut prev_lower = false;
+    for c in s.chars() {
+        if c.is_uppercase() && prev_lower {
+            dest.push('_');
+        }
+        prev_lower = c.is_lowercase() || c.is_ascii_digit();
+        dest.extend(c.to_lowercase());
+    }
+    dest
+}
+
+/// OCaml type variable used for the Rust type parameter `name`
+pub(crate) fn type_var(name: &str) -> String {
+    format!("'{}", snake_case(name))
+}
+
+/// Apply a type constructor to its arguments: `int list` or `(int, string) result`
+pub(crate) fn apply(args: &[Type], name: &str) -> String {
+    match args {
+        [] => name.to_string(),
+        [arg] => format!("{} {}", arg.atom(), name),
+        args => {
+            let args: Vec<_> = args.iter().map(|t| t.name.clone()).collect();
+            format!("({}) {}", args.join(", "), name)
+        }
+    }
+}
+
+/// Bigarray element types for each Rust element type
+fn bigarray_elt(ty: &str) -> Option<(&'static str, &'static str)> {
+    let elt = match ty {
+        "u8" => ("int", "int8_unsigned_elt"),
+        "i8" => ("int", "int8_signed_elt"),
+        "u16" => ("int", "int16_unsigned_elt"),
+        "i16" => ("int", "int16_signed_elt"),
+        "f32" => ("float", "float32_elt"),
+        "f64" => ("float", "float64_elt"),
+        "i64" => ("int64", "int64_elt"),
+        "i32" => ("int32", "int32_elt"),
+        "char" => ("char", "int8_unsigned_elt"),
+        "isize" => ("nativeint", "nativeint_elt"),
+        "CamlInt" => ("int", "int_elt"),
+        "Complex<f32>" => ("Complex.t", "complex32_elt"),
+        "Complex<f64>" => ("Complex.t", "complex64_elt"),
+        _ => return None,
+    };
+    Some(elt)
+}
+
+/// Name of a bigarray element type, including the argument of `Complex`
+fn elt_name(ident: &syn::Ident, args: &syn::PathArguments) -> Option<String> {
+    match args {
+        syn::PathArguments::None => Some(ident.to_string()),
+        syn::PathArguments::AngleBracketed(args) => match args.args.first() {
+            Some(syn::GenericArgument::Type(syn::Type::Path(p))) if args.args.len() == 1 => {
+                let arg = p.path.get_ident()?;
+                Some(format!("{}<{}>", ident, arg))
+            }
+            _ => None,
+        },
+        syn::PathArguments::Parenthesized(_) => None,
+    }
+}
+
+impl Context {
+    pub(crate) fn new(generics: &syn::Generics) -> Context {
+        Context {
+            generics: generics
+                .type_params()
+                .map(|param| param.ident.to_string())
+                .collect(),
+            vars: 0,
+        }
+    }
+
+    /// Fresh type variable, used for values without a static type
+    fn var(&mut self) -> Type {
+        let name = format!("'{}", (b'a' + (self.vars % 26) as u8) as char);
+        let name = if self.vars >= 26 {
+            format!("{}{}", name, self.vars / 26)
+        } else {
+            name
+        };
+        self.vars += 1;
+        Type::new(name)
+    }
+
+    fn args(&mut self, args: &syn::PathArguments) -> Vec<Type> {
+        match args {
+            syn::PathArguments::AngleBracketed(args) => args
+                .args
+                .iter()
+                .filter_map(|arg| match arg {
+                    syn::GenericArgument::Type(ty) => Some(self.ocaml_type(ty)),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// `Genarray` values can have either layout, so a type variable is used for the layout
+    fn bigarray(&mut self, name: &str, args: &syn::PathArguments) -> Type {
+        let elt = match args {
+            syn::PathArguments::AngleBracketed(args) => {
+                args.args.iter().find_map(|arg| match arg {
+                    syn::GenericArgument::Type(syn::Type::Path(p)) => {
+                        p.path.segments.last().and_then(|segment| {
+                            bigarray_elt(&elt_name(&segment.ident, &segment.arguments)?)
+                        })
+                    }
+                    _ => None,
+                })
+            }
+            _ => None,
+        };
+        let (t, elt) = match elt {
+            Some((t, elt)) => (t.to_string(), format!("Bigarray.{}", elt)),
+            None => (self.var().name, self.var().name),
+        };
+        let (module, layout) = match name {
+            "ArrayN" => ("Genarray".to_string(), self.var().name),
+            _ => (name.to_string(), "Bigarray.c_layout".to_string()),
+        };
+        Type::new(format!(
+            "({}, {}, {}) Bigarray.{}.t",
+            t, elt, layout, module
+        ))
+    }
+
+    fn path(&mut self, path: &syn::Path) -> Type {
+        let segment = match path.segments.last() {
+            Some(segment) => segment,
+            None => return self.var(),
+        };
+        let name = segment.ident.to_string();
+        if path.segments.len() == 1 && self.generics.contains(&name) {
+            return Type::new(type_var(&name));
+        }
+        let mut args = self.args(&segment.arguments);
+        let first = |args: &mut Vec<Type>| {
+            if args.is_empty() {
+                Type::new("'a")
+            } else {
+                args.remove(0)
+            }
+        };
+        let name = match name.as_str() {
+            "isize" | "usize" | "Int" | "Uint" | "i8" | "u8" | "i16" | "u16" | "OCamlInt" => "int",
+            "i32" | "u32" | "OCamlInt32" => "int32",
+            "i64" | "u64" | "OCamlInt64" => "int64",
+            "f32" | "f64" | "OCamlFloat" => "float",
+            "bool" | "OCamlBool" => "bool",
+            "char" => "char",
+            "String" | "str" | "OCamlBytes" => "string",
+            "Value" | "Raw" => "Obj.t",
+            "Result" | "Box" | "OCaml" | "OCamlRef" | "Pointer" => return first(&mut args),
+            "Option" => return Type::new(apply(&[first(&mut args)], "option")),
+            "Vec" | "Array" => return Type::new(apply(&[first(&mut args)], "array")),
+            "FloatArray" => return Type::new("float array"),
+            "List" | "LinkedList" | "OCamlList" => {
+                return Type::new(apply(&[first(&mut args)], "list"))
+            }
+            "BTreeMap" if args.len() == 2 => {
+                let pair = Type {
+                    name: format!("{} * {}", args[0].atom(), args[1].atom()),
+                    tuple: true,
+                };
+                return Type::new(apply(&[pair], "list"));
+            }
+            "Array1" | "Array2" | "Array3" | "ArrayN" => {
+                return self.bigarray(&name, &segment.arguments);
+            }
+            // Function types are always parenthesized
+            "Fn1" | "Fn2" | "Fn3" | "Fn4" | "Fn5" if !args.is_empty() => {
+                let ret = args.pop().unwrap();
+                let mut parts: Vec<_> = args.iter().map(Type::atom).collect();
+                parts.push(ret.name);
+                return Type::new(format!("({})", parts.join(" -> ")));
+            }
+            _ => return Type::new(apply(&args, &snake_case(&name))),
+        };
+        Type::new(name)
+    }
+
+    pub(crate) fn ocaml_type(&mut self, ty: &syn::Type) -> Type {
+        match ty {
+            syn::Type::Path(p) => self.path(&p.path),
+            syn::Type::Reference(r) => match r.elem.as_ref() {
+                syn::Type::Slice(s) => self.slice(&s.elem),
+                elem => self.ocaml_type(elem),
+            },
+            syn::Type::Slice(s) => self.slice(&s.elem),
+            syn::Type::Array(a) => Type::new(apply(&[self.ocaml_type(&a.elem)], "array")),
+            syn::Type::Paren(p) => self.ocaml_type(&p.elem),
+            syn::Type::Group(g) => self.ocaml_type(&g.elem),
+            syn::Type::Tuple(t) if t.elems.is_empty() => Type::new("unit"),
+            syn::Type::Tuple(t) if t.elems.len() == 1 => self.ocaml_type(&t.elems[0]),
+            syn::Type::Tuple(t) => {
+                let elems: Vec<_> = t
+                    .elems
+                    .iter()
+                    .map(|ty| self.ocaml_type(ty).atom())
+                    .collect();
+                Type {
+                    name: elems.join(" * "),
+                    tuple: true,
+                }
+            }
+            _ => self.var(),
+        }
+    }
+
+    fn slice(&mut self, elem: &syn::Type) -> Type {
+        match elem {
+            syn::Type::Path(p) if p.path.is_ident("u8") => Type::new("bytes"),
+            elem => Type::new(apply(&[self.ocaml_type(elem)], "array")),
+        }
+    }
+}
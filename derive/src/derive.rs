@@ -4,6 +4,136 @@ use quote::quote;
 struct Attrs {
     unboxed: bool,
     floats: bool,
+    polymorphic: bool,
+    discriminants: bool,
+    bound: Option<Vec<syn::WherePredicate>>,
+    tag: Option<usize>,
+}
+
+#[derive(Default)]
+struct FieldAttrs {
+    skip: bool,
+    default: Option<proc_macro2::TokenStream>,
+    with: Option<syn::Path>,
+    index: Option<usize>,
+}
+
+impl FieldAttrs {
+    fn encode(&self, b: impl quote::ToTokens) -> proc_macro2::TokenStream {
+        match &self.with {
+            Some(m) => quote!(#m::into_value(#b, gc)),
+            None => quote!(ocaml::IntoValue::into_value(#b, gc)),
+        }
+    }
+
+    fn decode(&self, v: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        match &self.with {
+            Some(m) => quote!(#m::from_value(#v)),
+            None => quote!(ocaml::FromValue::from_value(#v)),
+        }
+    }
+
+    fn try_decode(&self, v: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        match &self.with {
+            Some(m) => quote!(#m::try_from_value(#v)),
+            None => quote!(ocaml::TryFromValue::try_from_value(#v)),
+        }
+    }
+
+    fn default(&self) -> proc_macro2::TokenStream {
+        match &self.default {
+            Some(d) => d.clone(),
+            None => quote!(Default::default()),
+        }
+    }
+}
+
+/// Describes where each field of a variant is stored in the OCaml block, skipped fields have no
+/// index and fields without an explicit index take the next free slot
+struct Layout {
+    fields: Vec<(FieldAttrs, Option<usize>)>,
+    size: usize,
+}
+
+impl Layout {
+    fn new(fields: &syn::Fields) -> Layout {
+        let mut next = 0;
+        let mut size = 0;
+        let mut used = Vec::new();
+        let fields = fields
+            .iter()
+            .map(|field| {
+                let attrs = field_attrs(field);
+                if attrs.skip {
+                    if attrs.index.is_some() {
+                        panic!("in ocaml attrs a field cannot be both skipped and indexed")
+                    }
+                    return (attrs, None);
+                }
+                while attrs.index.is_none() && used.contains(&next) {
+                    next += 1;
+                }
+                let index = attrs.index.unwrap_or(next);
+                if used.contains(&index) {
+                    panic!("ocaml field index {} is used by more than one field", index)
+                }
+                used.push(index);
+                next = index + 1;
+                size = size.max(next);
+                (attrs, Some(index))
+            })
+            .collect();
+        Layout { fields, size }
+    }
+
+    /// Fields that are stored in the OCaml block along with their index
+    fn stored(&self) -> impl Iterator<Item = (&FieldAttrs, usize)> {
+        self.fields
+            .iter()
+            .filter_map(|(attrs, index)| index.map(|i| (attrs, i)))
+    }
+
+    /// Minimum size of a block containing every stored field without a default
+    fn required(&self) -> usize {
+        self.fields
+            .iter()
+            .filter_map(|(attrs, index)| match index {
+                Some(i) if attrs.default.is_none() => Some(i + 1),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Generates the expression used to read the field at position `idx` from `value`
+    fn read(
+        &self,
+        idx: usize,
+        block: proc_macro2::TokenStream,
+        read: impl Fn(&FieldAttrs, usize) -> proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        self.read_at(idx, block, 0, read)
+    }
+
+    /// Same as `read` for blocks where the fields start at `offset`
+    fn read_at(
+        &self,
+        idx: usize,
+        block: proc_macro2::TokenStream,
+        offset: usize,
+        read: impl Fn(&FieldAttrs, usize) -> proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        let (attrs, index) = &self.fields[idx];
+        match (index, &attrs.default) {
+            (None, _) => attrs.default(),
+            (Some(i), None) => read(attrs, *i + offset),
+            (Some(i), Some(default)) => {
+                let i = *i + offset;
+                let x = read(attrs, i);
+                quote!(if ocaml::sys::wosize_val(#block.0) > #i { #x } else { #default })
+            }
+        }
+    }
 }
 
 fn is_ocaml(path: &syn::Path) -> bool {
@@ -15,6 +145,73 @@ fn is_ocaml(path: &syn::Path) -> bool {
             .map_or(false, |segment| segment.ident == "ocaml")
 }
 
+/// `PhantomData` fields aren't stored in OCaml values
+fn is_phantom(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(p) => {
+            matches!(p.path.segments.last(), Some(segment) if segment.ident == "PhantomData")
+        }
+        _ => false,
+    }
+}
+
+fn field_attrs(field: &syn::Field) -> FieldAttrs {
+    let mut attrs =
+        field
+            .attrs
+            .iter()
+            .find(|attr| is_ocaml(&attr.path))
+            .map_or(Default::default(), |attr| {
+                if let Ok(syn::Meta::List(ref list)) = attr.parse_meta() {
+                    list.nested
+                        .iter()
+                        .fold(Default::default(), |mut acc: FieldAttrs, meta| match meta {
+                            syn::NestedMeta::Meta(syn::Meta::Path(ref path)) => {
+                                if path.is_ident("skip") {
+                                    acc.skip = true;
+                                } else if path.is_ident("default") {
+                                    acc.default = Some(quote!(Default::default()));
+                                } else {
+                                    panic!("unexpected ocaml field attribute parameter")
+                                }
+                                acc
+                            }
+                            syn::NestedMeta::Meta(syn::Meta::NameValue(ref nv)) => {
+                                match &nv.lit {
+                                    syn::Lit::Str(s) if nv.path.is_ident("default") => {
+                                        let expr: syn::Expr = s
+                                            .parse()
+                                            .expect("ocaml default must be a valid expression");
+                                        acc.default = Some(quote!(#expr));
+                                    }
+                                    syn::Lit::Str(s) if nv.path.is_ident("with") => {
+                                        acc.with = Some(
+                                            s.parse()
+                                                .expect("ocaml with must be a valid module path"),
+                                        );
+                                    }
+                                    syn::Lit::Int(i) if nv.path.is_ident("index") => {
+                                        acc.index = Some(
+                                            i.base10_parse()
+                                                .expect("ocaml index must be a positive integer"),
+                                        );
+                                    }
+                                    _ => panic!("unexpected ocaml field attribute parameter"),
+                                }
+                                acc
+                            }
+                            _ => panic!("unexpected ocaml field attribute parameter"),
+                        })
+                } else {
+                    panic!("ocaml attribute must take a list of valid attributes in parentheses")
+                }
+            });
+    if is_phantom(&field.ty) && attrs.index.is_none() {
+        attrs.skip = true;
+    }
+    attrs
+}
+
 fn variant_attrs(attrs: &[syn::Attribute]) -> Attrs {
     attrs
         .iter()
@@ -38,6 +235,12 @@ fn variant_attrs(attrs: &[syn::Attribute]) -> Attrs {
                                     }
                                     acc.floats = true;
                                     acc
+                                } else if ident == "polymorphic" {
+                                    acc.polymorphic = true;
+                                    acc
+                                } else if ident == "discriminants" {
+                                    acc.discriminants = true;
+                                    acc
                                 } else {
                                     panic!("unexpected ocaml attribute parameter {}", ident)
                                 }
@@ -45,7 +248,49 @@ fn variant_attrs(attrs: &[syn::Attribute]) -> Attrs {
                                 acc
                             }
                         }
-                     _ => panic!("unexpected ocaml attribute parameter"),
+                        syn::NestedMeta::Meta(syn::Meta::NameValue(ref nv))
+                            if nv.path.is_ident("bound") =>
+                        {
+                            match nv.lit {
+                                syn::Lit::Str(ref s) => {
+                                    let bound = s
+                                        .parse_with(
+                                            syn::punctuated::Punctuated::<
+                                                syn::WherePredicate,
+                                                syn::Token![,],
+                                            >::parse_terminated,
+                                        )
+                                        .expect("ocaml bound must be a list of where predicates");
+                                    acc.bound = Some(bound.into_iter().collect());
+                                    acc
+                                }
+                                _ => panic!("ocaml bound must be a string"),
+                            }
+                        }
+                        syn::NestedMeta::Meta(syn::Meta::NameValue(ref nv))
+                            if nv.path.is_ident("tag") =>
+                        {
+                            match nv.lit {
+                                syn::Lit::Int(ref i) => {
+                                    acc.tag = Some(
+                                        i.base10_parse()
+                                            .expect("ocaml tag must be a positive integer"),
+                                    );
+                                    acc
+                                }
+                                _ => panic!("ocaml tag must be a positive integer"),
+                            }
+                        }
+                        // Only used by ocaml-build when generating OCaml type definitions
+                        syn::NestedMeta::Meta(syn::Meta::NameValue(ref nv))
+                            if nv.path.is_ident("sig") =>
+                        {
+                            match nv.lit {
+                                syn::Lit::Str(_) => acc,
+                                _ => panic!("ocaml sig must be a string"),
+                            }
+                        }
+                        _ => panic!("unexpected ocaml attribute parameter"),
                     })
             } else {
                 panic!("ocaml attribute must take a list of valid attributes in parentheses")
@@ -53,46 +298,316 @@ fn variant_attrs(attrs: &[syn::Attribute]) -> Attrs {
         })
 }
 
+fn check_polymorphic(s: &synstructure::Structure) -> bool {
+    let attrs = variant_attrs(&s.ast().attrs);
+    if attrs.polymorphic {
+        if let syn::Data::Struct(_) = s.ast().data {
+            panic!("ocaml can only derive polymorphic variants for enums")
+        }
+        if attrs.unboxed || attrs.floats {
+            panic!("ocaml cannot derive unboxed or float arrays for polymorphic variants")
+        }
+        if attrs.discriminants {
+            panic!("ocaml polymorphic variants are identified by their name, remove discriminants")
+        }
+        for variant in s.variants() {
+            if variant_attrs(variant.ast().attrs).tag.is_some()
+                || variant.ast().discriminant.is_some()
+            {
+                panic!(
+                    "ocaml polymorphic variants are identified by their name, remove the tag from {}",
+                    variant.ast().ident
+                )
+            }
+        }
+    }
+    attrs.polymorphic
+}
+
+/// Largest tag of a constructor with arguments, larger tags are reserved by the OCaml runtime
+const MAX_BLOCK_TAG: usize = 245;
+
+fn discriminant(expr: &syn::Expr) -> usize {
+    match expr {
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Int(i),
+            ..
+        }) => i
+            .base10_parse()
+            .expect("ocaml tag must be a positive integer"),
+        _ => panic!("ocaml can only use integer literals as discriminants"),
+    }
+}
+
+/// OCaml tag of each variant, constant constructors and constructors with arguments are numbered
+/// separately in declaration order, a variant with `#[ocaml(tag = N)]` has tag `N` and the
+/// following variants of the same kind continue from it.
+///
+/// With `#[ocaml(discriminants)]` on the enum the tag is the Rust discriminant instead: either the
+/// explicit discriminant or one more than the discriminant of the previous variant, whatever its
+/// kind. `#[ocaml(tag = N)]` still overrides the tag of a variant without changing the discriminant
+fn variant_tags(s: &synstructure::Structure) -> Vec<usize> {
+    let attrs = variant_attrs(&s.ast().attrs);
+    if attrs.tag.is_some() {
+        panic!("ocaml tag can only be set on enum variants")
+    }
+    let mut next = [0, 0];
+    let mut next_discriminant = 0;
+    let mut used: Vec<(bool, usize, &syn::Ident)> = Vec::new();
+    s.variants()
+        .iter()
+        .map(|variant| {
+            let ident = variant.ast().ident;
+            let is_block = Layout::new(variant.ast().fields).size > 0;
+            let variant_attrs = variant_attrs(variant.ast().attrs);
+            if variant_attrs.discriminants {
+                panic!(
+                    "ocaml discriminants can only be set on enums, remove it from {}",
+                    ident
+                )
+            }
+            let tag = if attrs.discriminants {
+                let d = match variant.ast().discriminant {
+                    Some((_, expr)) => discriminant(expr),
+                    None => next_discriminant,
+                };
+                next_discriminant = d + 1;
+                variant_attrs.tag.unwrap_or(d)
+            } else {
+                let tag = variant_attrs.tag.unwrap_or(next[is_block as usize]);
+                next[is_block as usize] = tag + 1;
+                tag
+            };
+            if is_block && tag > MAX_BLOCK_TAG {
+                panic!(
+                    "ocaml constructors with arguments must have a tag below {}, {} has tag {}",
+                    MAX_BLOCK_TAG + 1,
+                    ident,
+                    tag
+                )
+            }
+            if let Some((_, _, other)) = used.iter().find(|(b, t, _)| *b == is_block && *t == tag) {
+                panic!("ocaml tag {} is used by both {} and {}", tag, other, ident)
+            }
+            used.push((is_block, tag, ident));
+            tag
+        })
+        .collect()
+}
+
+fn is_struct(s: &synstructure::Structure) -> bool {
+    matches!(s.ast().data, syn::Data::Struct(_))
+}
+
+/// Structs and single-variant enums can use `unboxed` and `floats_array`, without these attributes
+/// a single-variant enum is stored like any other enum
+fn is_record_like(s: &synstructure::Structure) -> bool {
+    is_struct(s) || s.variants().len() == 1
+}
+
+/// Enum variants with named fields are stored like OCaml inline records: a block tagged with the
+/// constructor tag, so even when every field is a float they are never stored as float arrays
+fn check_variant_attrs(attrs: &Attrs, variant: &syn::Ident) {
+    if attrs.floats {
+        panic!(
+            "ocaml inline records are not stored as float arrays, remove floats_array from {}",
+            variant
+        )
+    }
+    if attrs.unboxed {
+        panic!("ocaml cannot derive unboxed enum variants ({})", variant)
+    }
+    if attrs.bound.is_some() {
+        panic!(
+            "ocaml bound must be set on the enum, not on the variant {}",
+            variant
+        )
+    }
+}
+
+fn uses_param(tokens: proc_macro2::TokenStream, param: &syn::Ident) -> bool {
+    tokens.into_iter().any(|tt| match tt {
+        proc_macro2::TokenTree::Ident(ident) => &ident == param,
+        proc_macro2::TokenTree::Group(group) => uses_param(group.stream(), param),
+        _ => false,
+    })
+}
+
+/// Replaces the bounds added by synstructure with `T: Trait` for each type parameter used by a
+/// converted field, skipped fields and fields using `with` don't add bounds. The bounds can be set
+/// explicitly using `#[ocaml(bound = "...")]`
+fn add_bounds(s: &mut synstructure::Structure, traits: &[proc_macro2::TokenStream]) {
+    let predicates: Vec<syn::WherePredicate> = match variant_attrs(&s.ast().attrs).bound {
+        Some(bound) => bound,
+        None => s
+            .ast()
+            .generics
+            .type_params()
+            .map(|param| &param.ident)
+            .filter(|param| {
+                s.variants().iter().any(|variant| {
+                    variant.ast().fields.iter().any(|field| {
+                        let attrs = field_attrs(field);
+                        let ty = &field.ty;
+                        !attrs.skip && attrs.with.is_none() && uses_param(quote!(#ty), param)
+                    })
+                })
+            })
+            .map(|param| syn::parse_quote!(#param: #(#traits)+*))
+            .collect(),
+    };
+    s.add_bounds(synstructure::AddBounds::None);
+    for predicate in predicates {
+        s.add_where_predicate(predicate);
+    }
+}
+
+fn variant_hash(variant: &synstructure::VariantInfo) -> proc_macro2::TokenStream {
+    let name = format!("{}\0", variant.ast().ident);
+    quote!(ocaml::Value::new(ocaml::sys::caml_hash_variant(#name.as_ptr())))
+}
+
+fn polymorphic_intovalue_derive(mut s: synstructure::Structure) -> proc_macro::TokenStream {
+    let body: Vec<_> = s
+        .variants_mut()
+        .iter_mut()
+        .map(|variant| {
+            let layout = Layout::new(variant.ast().fields);
+            variant.filter(|b| !field_attrs(b.ast()).skip);
+            for b in variant.bindings_mut() {
+                b.style = synstructure::BindStyle::Move;
+            }
+            let name = variant.ast().ident.to_string();
+            let pat = variant.pat();
+            let fields: Vec<_> = layout
+                .stored()
+                .zip(variant.bindings())
+                .map(|((attrs, i), b)| (i, attrs.encode(b)))
+                .collect();
+            match layout.size {
+                0 => quote!(#pat => {
+                    value = unsafe { ocaml::Value::hash_variant(gc, #name, None) };
+                }),
+                1 => {
+                    let x = &fields[0].1;
+                    quote!(#pat => {
+                        value = unsafe { ocaml::Value::hash_variant(gc, #name, Some(#x)) };
+                    })
+                }
+                size => {
+                    // Constructors with several arguments carry a tuple as payload
+                    let fields = fields
+                        .iter()
+                        .map(|(i, x)| quote!(payload.store_field(gc, #i, #x)));
+                    quote!(#pat => {
+                        unsafe {
+                            let mut payload = ocaml::Value::alloc(gc, #size, ocaml::Tag(0));
+                            #(#fields;)*
+                            value = ocaml::Value::hash_variant(gc, #name, Some(payload));
+                        }
+                    })
+                }
+            }
+        })
+        .collect();
+
+    s.gen_impl(quote! {
+        gen unsafe impl ocaml::IntoValue for @Self {
+            fn into_value(self, gc: &ocaml::Runtime) -> ocaml::Value {
+                let mut value = ocaml::Value::unit();
+                match self {
+                    #(#body),*
+                }
+                value
+            }
+        }
+    })
+    .into()
+}
+
+fn polymorphic_fromvalue_derive(s: synstructure::Structure) -> proc_macro::TokenStream {
+    let body = s.variants().iter().map(|variant| {
+        let layout = Layout::new(variant.ast().fields);
+        let is_block = layout.size != 0;
+        let hash = variant_hash(variant);
+        let construct = variant.construct(|_, idx| {
+            if layout.size > 1 {
+                let payload = quote!(value.field::<ocaml::Value>(1));
+                layout.read(idx, payload.clone(), |attrs, i| {
+                    attrs.decode(quote!(#payload.field(#i)))
+                })
+            } else {
+                layout.read(idx, quote!(value), |attrs, _| {
+                    attrs.decode(quote!(value.field(1)))
+                })
+            }
+        });
+        quote!((#is_block, hash) if hash == #hash => {
+            #construct
+        })
+    });
+
+    s.gen_impl(quote! {
+        gen unsafe impl ocaml::FromValue for @Self {
+            fn from_value(value: ocaml::Value) -> Self {
+                unsafe {
+                    let is_block = value.is_block();
+                    let hash = if is_block { value.field(0) } else { value };
+                    match (is_block, hash) {
+                        #(#body),*
+                        _ => panic!("ocaml ffi: received unknown polymorphic variant while trying to convert ocaml value to rust"),
+                    }
+                }
+            }
+        }
+    })
+    .into()
+}
+
 pub fn intovalue_derive(mut s: synstructure::Structure) -> proc_macro::TokenStream {
-    let mut unit_tag = 0u8;
-    let mut non_unit_tag = 0u8;
-    let is_record_like = s.variants().len() == 1;
-    let body = s.variants_mut().to_vec().into_iter().map(|mut variant| {
-        let arity = variant.bindings().len();
+    add_bounds(&mut s, &[quote!(ocaml::IntoValue)]);
+    if check_polymorphic(&s) {
+        return polymorphic_intovalue_derive(s);
+    }
+    let tags = variant_tags(&s);
+    let is_record_like = is_record_like(&s);
+    let variants = s.variants_mut().to_vec().into_iter().zip(tags);
+    let body = variants.map(|(mut variant, tag)| {
+        let layout = Layout::new(variant.ast().fields);
+        let arity = layout.size;
+        variant.filter(|b| !field_attrs(b.ast()).skip);
         for b in variant.bindings_mut() {
             b.style = synstructure::BindStyle::Move;
         }
-        let tag_ref = if arity > 0 {
-            &mut non_unit_tag
-        } else {
-            &mut unit_tag
-        };
-        let tag = *tag_ref;
-        *tag_ref += 1;
         let attrs = variant_attrs(&variant.ast().attrs);
-        if (attrs.floats || attrs.unboxed) && !is_record_like {
-            panic!("ocaml cannot derive unboxed or float arrays for enums")
+        if !is_record_like {
+            check_variant_attrs(&attrs, variant.ast().ident);
         }
+        let mut fields = layout.stored();
         if arity == 0 {
             let init = quote!(value = unsafe { ocaml::Value::int(#tag as ocaml::Int)});
             variant.fold(init, |_, _| quote!())
         } else if attrs.floats {
-            let mut idx = 0usize;
             let init = quote!(
                 value = unsafe { ocaml::Value::alloc(gc, #arity, ocaml::Tag::DOUBLE_ARRAY) };
             );
             variant.fold(init, |acc, b| {
-                let i = idx;
-                idx += 1;
-                quote!(#acc; ocaml::array::set_double(gc, value, #i, *#b as f64).unwrap();)
+                let (_, i) = fields.next().unwrap();
+                quote!(#acc unsafe {
+                    <ocaml::Array<f64> as ocaml::FromValue>::from_value(value).set_double_unchecked(#i, #b as f64)
+                };)
             })
         } else if attrs.unboxed {
-            if variant.bindings().len() > 1 {
+            if arity > 1 || variant.bindings().len() > 1 {
                 panic!("ocaml cannot unboxed record with multiple fields")
             }
-            variant.each(|field| quote!(#field.into_value(gc)))
+            variant.each(|b| {
+                let (attrs, _) = fields.next().unwrap();
+                let x = attrs.encode(b);
+                quote!(value = #x)
+            })
         } else {
-            let mut idx = 0usize;
+            let tag = tag as u8;
             let ghost = (0..arity)
                 .map(|idx| quote!(unsafe { value.store_field(gc, #idx, ocaml::Value::unit()) }));
             let init = quote!(
@@ -100,9 +615,9 @@ pub fn intovalue_derive(mut s: synstructure::Structure) -> proc_macro::TokenStre
                 #(#ghost);*;
             );
             variant.fold(init, |acc, b| {
-                let i = idx;
-                idx += 1;
-                quote!(#acc unsafe { value.store_field(gc, #i, #b)};)
+                let (attrs, i) = fields.next().unwrap();
+                let x = attrs.encode(b);
+                quote!(#acc unsafe { value.store_field(gc, #i, #x)};)
             })
         }
     });
@@ -121,41 +636,46 @@ pub fn intovalue_derive(mut s: synstructure::Structure) -> proc_macro::TokenStre
     .into()
 }
 
-pub fn fromvalue_derive(s: synstructure::Structure) -> proc_macro::TokenStream {
-    let mut unit_tag = 0u8;
-    let mut non_unit_tag = 0u8;
-    let is_record_like = s.variants().len() == 1;
+pub fn fromvalue_derive(mut s: synstructure::Structure) -> proc_macro::TokenStream {
+    add_bounds(&mut s, &[quote!(ocaml::FromValue)]);
+    if check_polymorphic(&s) {
+        return polymorphic_fromvalue_derive(s);
+    }
+    let tags = variant_tags(&s);
+    let is_record_like = is_record_like(&s);
     let attrs = if is_record_like {
         variant_attrs(s.variants()[0].ast().attrs)
     } else {
         Attrs::default()
     };
-    let body = s.variants().iter().map(|variant| {
-        let arity = variant.bindings().len();
-        let tag_ref = if arity > 0 {
-            &mut non_unit_tag
-        } else {
-            &mut unit_tag
-        };
+    let body = s.variants().iter().zip(tags).map(|(variant, tag)| {
+        let layout = Layout::new(variant.ast().fields);
+        let arity = layout.size;
         let attrs = variant_attrs(&variant.ast().attrs);
-        if (attrs.floats || attrs.unboxed) && !is_record_like {
-            panic!("ocaml cannot derive unboxed records or float arrays for enums")
+        if !is_record_like {
+            check_variant_attrs(&attrs, variant.ast().ident);
         }
-        let tag = *tag_ref;
-        *tag_ref += 1;
+        let tag = tag as isize;
         let is_block = arity != 0;
         if attrs.unboxed {
             if arity > 1 {
                 panic!("ocaml cannot derive unboxed records with several fields")
             }
-            variant.construct(|_, _| quote!(ocaml::FromValue::from_value(value)))
+            variant.construct(|_, idx| match &layout.fields[idx] {
+                (attrs, None) => attrs.default(),
+                (attrs, Some(_)) => attrs.decode(quote!(value)),
+            })
         } else {
             let construct = variant.construct(|field, idx| {
                 if attrs.floats {
                     let ty = &field.ty;
-                    quote!(ocaml::array::get_double(value, #idx).unwrap() as #ty)
+                    layout.read(idx, quote!(value), |_, i| quote!(
+                        <ocaml::Array<f64> as ocaml::FromValue>::from_value(value).get_double_unchecked(#i) as #ty
+                    ))
                 } else {
-                    quote!(ocaml::FromValue::from_value(value.field(#idx)))
+                    layout.read(idx, quote!(value), |attrs, i| {
+                        attrs.decode(quote!(value.field(#i)))
+                    })
                 }
             });
             quote!((#is_block, #tag) => {
@@ -175,13 +695,13 @@ pub fn fromvalue_derive(s: synstructure::Structure) -> proc_macro::TokenStream {
         .into()
     } else {
         let tag = if !attrs.floats {
-            quote!({ unsafe { value.tag() } })
+            quote!(value.tag())
         } else {
             quote!({
-                if unsafe { value.tag() } != ocaml::Tag::DOUBLE_ARRAY {
+                if value.tag() != ocaml::Tag::DOUBLE_ARRAY {
                     panic!("ocaml ffi: trying to convert a value which is not a double array to an unboxed record")
                 };
-                0
+                ocaml::Tag(0)
             })
         };
         s.gen_impl(quote! {
@@ -189,7 +709,7 @@ pub fn fromvalue_derive(s: synstructure::Structure) -> proc_macro::TokenStream {
                 fn from_value(value: ocaml::Value) -> Self {
                     unsafe {
                         let is_block = value.is_block();
-                        let tag = if !is_block { value.int_val() as u8 } else { #tag.0 };
+                        let tag = if !is_block { value.int_val() } else { #tag.0 as isize };
                         match (is_block, tag) {
                             #(#body),*
                             _ => panic!("ocaml ffi: received unknown variant while trying to convert ocaml structure/enum to rust"),
@@ -200,3 +720,306 @@ pub fn fromvalue_derive(s: synstructure::Structure) -> proc_macro::TokenStream {
         }).into()
     }
 }
+
+/// Name used in decode error paths for the field at position `idx`
+fn field_name(field: &syn::Field, idx: usize) -> String {
+    field
+        .ident
+        .as_ref()
+        .map_or_else(|| idx.to_string(), |ident| ident.to_string())
+}
+
+/// Generates the construction of `variant` from the block `block`, wrapping field errors with their
+/// path, enum variants also add the constructor name
+fn try_construct(
+    variant: &synstructure::VariantInfo,
+    layout: &Layout,
+    block: proc_macro2::TokenStream,
+    is_enum: bool,
+) -> proc_macro2::TokenStream {
+    let constructor = variant.ast().ident.to_string();
+    variant.construct(|field, idx| {
+        let name = field_name(field, idx);
+        let path = if is_enum {
+            quote!(.field(#name).field(#constructor))
+        } else {
+            quote!(.field(#name))
+        };
+        layout.read(idx, block.clone(), |attrs, i| {
+            let x = attrs.try_decode(quote!(#block.field(#i)));
+            quote!(#x.map_err(|e| e #path)?)
+        })
+    })
+}
+
+fn polymorphic_tryfromvalue_derive(s: synstructure::Structure) -> proc_macro::TokenStream {
+    let expected = format!("a polymorphic variant of {}", s.ast().ident);
+    let body = s.variants().iter().map(|variant| {
+        let layout = Layout::new(variant.ast().fields);
+        let is_block = layout.size != 0;
+        let hash = variant_hash(variant);
+        let construct = if layout.size > 1 {
+            let constructor = variant.ast().ident.to_string();
+            let (required, size) = (layout.required(), layout.size);
+            let construct = try_construct(variant, &layout, quote!(payload), true);
+            quote!({
+                let payload = value.field::<ocaml::Value>(1);
+                ocaml::DecodeError::check_block_range(payload, ocaml::Tag(0), #required, #size)
+                    .map_err(|e| e.field(#constructor))?;
+                #construct
+            })
+        } else {
+            let constructor = variant.ast().ident.to_string();
+            variant.construct(|field, idx| {
+                let name = field_name(field, idx);
+                layout.read(idx, quote!(value), |attrs, _| {
+                    let x = attrs.try_decode(quote!(value.field(1)));
+                    quote!(#x.map_err(|e| e.field(#name).field(#constructor))?)
+                })
+            })
+        };
+        quote!((#is_block, hash) if hash == #hash => Ok(#construct))
+    });
+
+    s.gen_impl(quote! {
+        gen unsafe impl ocaml::TryFromValue for @Self {
+            fn try_from_value(value: ocaml::Value) -> Result<Self, ocaml::DecodeError> {
+                unsafe {
+                    let is_block = value.is_block();
+                    if is_block {
+                        ocaml::DecodeError::check_block(value, ocaml::Tag(0), 2)?;
+                    }
+                    let hash = if is_block { value.field(0) } else { value };
+                    match (is_block, hash) {
+                        #(#body,)*
+                        _ => Err(ocaml::DecodeError::new(#expected, value)),
+                    }
+                }
+            }
+        }
+    })
+    .into()
+}
+
+pub fn tryfromvalue_derive(mut s: synstructure::Structure) -> proc_macro::TokenStream {
+    add_bounds(&mut s, &[quote!(ocaml::TryFromValue)]);
+    if check_polymorphic(&s) {
+        return polymorphic_tryfromvalue_derive(s);
+    }
+    let is_enum = matches!(s.ast().data, syn::Data::Enum(_));
+    let is_record_like = is_record_like(&s);
+    let tags = variant_tags(&s);
+    let mut constants = Vec::new();
+    let mut blocks = Vec::new();
+    for (variant, tag) in s.variants().iter().zip(tags) {
+        let layout = Layout::new(variant.ast().fields);
+        let attrs = variant_attrs(variant.ast().attrs);
+        if !is_record_like {
+            check_variant_attrs(&attrs, variant.ast().ident);
+        }
+        if attrs.unboxed {
+            if layout.size > 1 {
+                panic!("ocaml cannot derive unboxed records with several fields")
+            }
+            let construct = variant.construct(|field, idx| match &layout.fields[idx] {
+                (attrs, None) => attrs.default(),
+                (attrs, Some(_)) => {
+                    let name = field_name(field, idx);
+                    let x = attrs.try_decode(quote!(value));
+                    quote!(#x.map_err(|e| e.field(#name))?)
+                }
+            });
+            return s
+                .gen_impl(quote! {
+                    gen unsafe impl ocaml::TryFromValue for @Self {
+                        fn try_from_value(value: ocaml::Value) -> Result<Self, ocaml::DecodeError> {
+                            Ok(#construct)
+                        }
+                    }
+                })
+                .into();
+        }
+        let (required, size) = (layout.required(), layout.size);
+        if layout.size == 0 {
+            let tag = tag as isize;
+            let construct =
+                variant.construct(|_, idx| layout.read(idx, quote!(value), |_, _| quote!()));
+            constants.push(quote!(#tag => Ok(#construct)));
+        } else if attrs.floats {
+            let construct = variant.construct(|field, idx| {
+                let ty = &field.ty;
+                layout.read(idx, quote!(value), |_, i| quote!(
+                    <ocaml::Array<f64> as ocaml::FromValue>::from_value(value).get_double_unchecked(#i) as #ty
+                ))
+            });
+            blocks.push(quote!(_ => {
+                ocaml::DecodeError::check_block_range(value, ocaml::Tag::DOUBLE_ARRAY, #required, #size)?;
+                Ok(#construct)
+            }));
+        } else {
+            let tag = tag as u8;
+            let construct = try_construct(variant, &layout, quote!(value), is_enum);
+            blocks.push(quote!(#tag => {
+                ocaml::DecodeError::check_block_range(value, ocaml::Tag(#tag), #required, #size)?;
+                Ok(#construct)
+            }));
+        }
+    }
+
+    let expected = if is_enum {
+        format!("a constructor of {}", s.ast().ident)
+    } else {
+        format!("a record of type {}", s.ast().ident)
+    };
+    s.gen_impl(quote! {
+        gen unsafe impl ocaml::TryFromValue for @Self {
+            fn try_from_value(value: ocaml::Value) -> Result<Self, ocaml::DecodeError> {
+                unsafe {
+                    if value.is_block() {
+                        match value.tag().0 {
+                            #(#blocks,)*
+                            #[allow(unreachable_patterns)]
+                            _ => Err(ocaml::DecodeError::new(#expected, value)),
+                        }
+                    } else {
+                        match value.int_val() {
+                            #(#constants,)*
+                            _ => Err(ocaml::DecodeError::new(#expected, value)),
+                        }
+                    }
+                }
+            }
+        }
+    })
+    .into()
+}
+
+/// Name used to look up the exception for `variant`, set using `#[ocaml(name = "...")]`
+fn exception_name(variant: &synstructure::VariantInfo) -> String {
+    let mut name = variant.ast().ident.to_string();
+    for attr in variant
+        .ast()
+        .attrs
+        .iter()
+        .filter(|attr| is_ocaml(&attr.path))
+    {
+        match attr.parse_meta() {
+            Ok(syn::Meta::List(list)) => {
+                for meta in list.nested.iter() {
+                    match meta {
+                        syn::NestedMeta::Meta(syn::Meta::NameValue(nv))
+                            if nv.path.is_ident("name") =>
+                        {
+                            match &nv.lit {
+                                syn::Lit::Str(s) => name = s.value(),
+                                _ => panic!("ocaml exception name must be a string"),
+                            }
+                        }
+                        _ => panic!("unexpected ocaml exception attribute parameter"),
+                    }
+                }
+            }
+            _ => panic!("ocaml attribute must take a list of valid attributes in parentheses"),
+        }
+    }
+    name
+}
+
+pub fn exception_derive(mut s: synstructure::Structure) -> proc_macro::TokenStream {
+    if is_struct(&s) {
+        panic!("ocaml can only derive exceptions for enums")
+    }
+    add_bounds(
+        &mut s,
+        &[quote!(ocaml::IntoValue), quote!(ocaml::FromValue)],
+    );
+
+    let decode: Vec<_> = s
+        .variants()
+        .iter()
+        .map(|variant| {
+            let name = exception_name(variant);
+            let layout = Layout::new(variant.ast().fields);
+            // Exception arguments are stored after the exception constructor
+            let construct = variant.construct(|_, idx| {
+                layout.read_at(idx, quote!(exn), 1, |attrs, i| {
+                    attrs.decode(quote!(exn.field(#i)))
+                })
+            });
+            quote!(if let Some(c) = ocaml::Error::named(#name) {
+                if c.0 == constructor.0 {
+                    return Some(#construct);
+                }
+            })
+        })
+        .collect();
+
+    let encode: Vec<_> = s
+        .variants_mut()
+        .iter_mut()
+        .map(|variant| {
+            let name = exception_name(variant);
+            let layout = Layout::new(variant.ast().fields);
+            variant.filter(|b| !field_attrs(b.ast()).skip);
+            for b in variant.bindings_mut() {
+                b.style = synstructure::BindStyle::Move;
+            }
+            let pat = variant.pat();
+            let size = layout.size + 1;
+            let fields: Vec<_> = layout
+                .stored()
+                .zip(variant.bindings())
+                .map(|((attrs, i), b)| {
+                    let i = i + 1;
+                    let x = attrs.encode(b);
+                    quote!(exn.store_field(gc, #i, #x))
+                })
+                .collect();
+            let exn = if layout.size == 0 {
+                quote!(constructor)
+            } else {
+                quote!({
+                    let mut exn = ocaml::Value::alloc(gc, #size, ocaml::Tag(0));
+                    // The constructor is a global root, so it's looked up again after allocating
+                    exn.store_field(gc, 0, ocaml::Error::named(#name).unwrap_or(constructor));
+                    #(#fields;)*
+                    exn
+                })
+            };
+            quote!(#pat => {
+                let constructor = match ocaml::Error::named(#name) {
+                    Some(c) => c,
+                    None => return Err(ocaml::Error::Message(concat!(
+                        "exception ", #name, " has not been registered with the OCaml runtime"
+                    ).into())),
+                };
+                Ok(#exn)
+            })
+        })
+        .collect();
+
+    s.gen_impl(quote! {
+        gen unsafe impl ocaml::Exception for @Self {
+            fn into_exception(self, gc: &ocaml::Runtime) -> Result<ocaml::Value, ocaml::Error> {
+                unsafe {
+                    match self {
+                        #(#encode),*
+                    }
+                }
+            }
+
+            fn from_exception(exn: ocaml::Value) -> Option<Self> {
+                unsafe {
+                    let constructor = if exn.tag() == ocaml::Tag::OBJECT {
+                        exn
+                    } else {
+                        exn.field::<ocaml::Value>(0)
+                    };
+                    #(#decode)*
+                    None
+                }
+            }
+        }
+    })
+    .into()
+}
@@ -5,6 +5,7 @@ use proc_macro::TokenStream;
 use quote::quote;
 
 mod derive;
+mod object;
 
 fn check_func(item_fn: &mut syn::ItemFn) {
     if item_fn.sig.asyncness.is_some() {
@@ -30,6 +31,87 @@ fn check_func(item_fn: &mut syn::ItemFn) {
     });
 }
 
+/// Arguments accepted by `func` and `bytecode_func`: an optional name for the `gc` handle, which
+/// has to come first, and `unchecked`
+struct FuncArgs {
+    gc_name: syn::Ident,
+    unchecked: bool,
+}
+
+/// Crates can set this variable from their build script, using
+/// `cargo:rustc-env=OCAML_FUNC_UNCHECKED=1`, to skip the argument checks in every function
+const UNCHECKED_ENV: &str = "OCAML_FUNC_UNCHECKED";
+
+impl FuncArgs {
+    fn parse(attribute: TokenStream, span: proc_macro2::Span) -> syn::Result<FuncArgs> {
+        use syn::parse::Parser;
+
+        let mut args = FuncArgs {
+            gc_name: syn::Ident::new("gc", span),
+            unchecked: std::env::var_os(UNCHECKED_ENV).map_or(false, |x| x != "0"),
+        };
+        let parser = syn::punctuated::Punctuated::<syn::Ident, syn::Token![,]>::parse_terminated;
+        let mut unchecked = false;
+        for (i, ident) in parser.parse(attribute)?.into_iter().enumerate() {
+            if ident == "unchecked" && !unchecked {
+                unchecked = true;
+            } else if i == 0 && !is_typo(&ident.to_string(), "unchecked") {
+                args.gc_name = ident;
+            } else if i == 0 {
+                return Err(syn::Error::new(
+                    ident.span(),
+                    format!("unexpected argument `{}`, did you mean `unchecked`?", ident),
+                ));
+            } else {
+                return Err(syn::Error::new(
+                    ident.span(),
+                    format!(
+                        "unexpected argument `{}`, the `gc` handle name has to be the first \
+                         argument followed by an optional `unchecked`",
+                        ident
+                    ),
+                ));
+            }
+        }
+        args.unchecked |= unchecked;
+        Ok(args)
+    }
+}
+
+/// Returns true when `s` is within two edits of `expected` without being equal to it
+fn is_typo(s: &str, expected: &str) -> bool {
+    let expected: Vec<char> = expected.chars().collect();
+    let mut prev: Vec<usize> = (0..=expected.len()).collect();
+    for (i, a) in s.chars().enumerate() {
+        let mut row = vec![i + 1];
+        for (j, b) in expected.iter().enumerate() {
+            let cost = if a == *b { 0 } else { 1 };
+            row.push((prev[j] + cost).min(prev[j + 1] + 1).min(row[j] + 1));
+        }
+        prev = row;
+    }
+    let distance = prev[expected.len()];
+    distance > 0 && distance <= 2
+}
+
+/// Converts the parameter `ident` using `FromValue::from_value_checked`, raising an OCaml exception
+/// when the value is rejected. `FromValue::from_value` is used instead for `unchecked` functions
+fn convert_param(ident: &syn::Ident, args: &FuncArgs) -> proc_macro2::TokenStream {
+    let gc_name = &args.gc_name;
+    if args.unchecked {
+        return quote! { let #ident = ocaml::FromValue::from_value(#ident); };
+    }
+
+    quote! {
+        let #ident = match ocaml::FromValue::from_value_checked(#ident) {
+            Ok(x) => x,
+            Err(e) => {
+                return ocaml::IntoValue::into_value(Err::<ocaml::Value, ocaml::Error>(e), &#gc_name)
+            }
+        };
+    }
+}
+
 /// `func` is used export Rust functions to OCaml, performing the necessary wrapping/unwrapping
 /// automatically.
 ///
@@ -38,6 +120,11 @@ fn check_func(item_fn: &mut syn::ItemFn) {
 /// - Defines a bytecode function automatically for functions that take more than 5 arguments. The
 /// bytecode function for `my_func` would be `my_func_bytecode`
 /// - Allows for an optional ident argument specifying the name of the `gc` handle parameter
+/// - Arguments are converted using `FromValue::from_value_checked`, which checks the kind and
+///   dimensions of bigarrays. The checks can be disabled for a function using
+///   `#[ocaml::func(unchecked)]`, or `#[ocaml::func(gc, unchecked)]` along with a handle name. To
+///   disable them for a whole crate add `println!("cargo:rustc-env=OCAML_FUNC_UNCHECKED=1")` to
+///   its build script
 #[proc_macro_attribute]
 pub fn ocaml_func(attribute: TokenStream, item: TokenStream) -> TokenStream {
     let mut item_fn: syn::ItemFn = syn::parse(item).unwrap();
@@ -46,10 +133,11 @@ pub fn ocaml_func(attribute: TokenStream, item: TokenStream) -> TokenStream {
     let name = &item_fn.sig.ident;
     let unsafety = &item_fn.sig.unsafety;
     let constness = &item_fn.sig.constness;
-    let mut gc_name = syn::Ident::new("gc", name.span());
-    if let Ok(ident) = syn::parse::<syn::Ident>(attribute) {
-        gc_name = ident;
-    }
+    let func_args = match FuncArgs::parse(attribute, name.span()) {
+        Ok(args) => args,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let gc_name = &func_args.gc_name;
 
     let (returns, rust_return_type) = match &item_fn.sig.output {
         syn::ReturnType::Default => (false, None),
@@ -93,10 +181,7 @@ pub fn ocaml_func(attribute: TokenStream, item: TokenStream) -> TokenStream {
     let convert_params: Vec<_> = args
         .iter()
         .filter_map(|arg| match arg {
-            Some(ident) => {
-                let ident = ident.ident.clone();
-                Some(quote! { let #ident = ocaml::FromValue::from_value(#ident); })
-            }
+            Some(ident) => Some(convert_param(&ident.ident, &func_args)),
             None => None,
         })
         .collect();
@@ -155,7 +240,7 @@ pub fn ocaml_func(attribute: TokenStream, item: TokenStream) -> TokenStream {
         let bytecode = {
             let mut bc = item_fn.clone();
             bc.sig.ident = syn::Ident::new(&format!("{}_bytecode", name), name.span());
-            ocaml_bytecode_func_impl(bc, gc_name, Some(name))
+            ocaml_bytecode_func_impl(bc, &func_args, Some(name))
         };
 
         let r = quote! {
@@ -169,6 +254,51 @@ pub fn ocaml_func(attribute: TokenStream, item: TokenStream) -> TokenStream {
     gen.into()
 }
 
+/// `sig` sets the OCaml type of a function exported using `func`, `native_func` or
+/// `bytecode_func`. It has no effect on the generated code and is read by `ocaml-build` when
+/// generating OCaml stubs
+///
+/// ```rust,ignore
+/// #[ocaml::sig("int -> int")]
+/// #[ocaml::native_func]
+/// pub fn incr(value: ocaml::Value) -> ocaml::Value {
+///     ocaml::Value::int(value.int_val() + 1)
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn ocaml_sig(attribute: TokenStream, item: TokenStream) -> TokenStream {
+    let _: syn::LitStr = syn::parse(attribute).expect("ocaml sig must be a string literal");
+    item
+}
+
+/// `object` is used on a trait declaration to wrap OCaml objects, a proxy type named after the
+/// trait (`{Trait}Object`, or the ident argument) is generated that implements the trait by
+/// calling the public method with the same name on the wrapped object.
+///
+/// - Methods must take `&self`, arguments are converted using `IntoValue` and the return value
+///   using `FromValue`
+/// - Methods returning `Result<T, E>` where `E: From<ocaml::Error>` return an error when the
+///   method is missing or raises an exception, other methods panic
+/// - `#[ocaml(name = "...")]` sets the name of the OCaml method
+///
+/// ```rust,ignore
+/// #[ocaml::object]
+/// pub trait Counter {
+///     fn incr(&self, n: ocaml::Int) -> Result<ocaml::Int, ocaml::Error>;
+/// }
+///
+/// #[ocaml::func]
+/// pub fn incr_twice(c: CounterObject) -> Result<ocaml::Int, ocaml::Error> {
+///     c.incr(1)?;
+///     c.incr(1)
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn ocaml_object(attribute: TokenStream, item: TokenStream) -> TokenStream {
+    let item: syn::ItemTrait = syn::parse(item).expect("ocaml object must be a trait");
+    object::object(attribute, item).into()
+}
+
 /// `native_func` is used export Rust functions to OCaml, it has much lower overhead than `func`
 /// and expects all arguments and return type to to be `Value`.
 ///
@@ -257,21 +387,23 @@ pub fn ocaml_native_func(attribute: TokenStream, item: TokenStream) -> TokenStre
 ///
 /// - Automatic type conversion for arguments/return value
 /// - Allows for an optional ident argument specifying the name of the `gc` handle parameter
+/// - Arguments are converted using `FromValue::from_value_checked` unless `unchecked` is passed
 #[proc_macro_attribute]
 pub fn ocaml_bytecode_func(attribute: TokenStream, item: TokenStream) -> TokenStream {
     let item_fn: syn::ItemFn = syn::parse(item).unwrap();
-    let mut gc_name = syn::Ident::new("gc", item_fn.sig.ident.span());
-    if let Ok(ident) = syn::parse::<syn::Ident>(attribute) {
-        gc_name = ident;
-    }
-    ocaml_bytecode_func_impl(item_fn, gc_name, None).into()
+    let func_args = match FuncArgs::parse(attribute, item_fn.sig.ident.span()) {
+        Ok(args) => args,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    ocaml_bytecode_func_impl(item_fn, &func_args, None).into()
 }
 
 fn ocaml_bytecode_func_impl(
     mut item_fn: syn::ItemFn,
-    gc_name: syn::Ident,
+    func_args: &FuncArgs,
     original: Option<&proc_macro2::Ident>,
 ) -> proc_macro2::TokenStream {
+    let gc_name = &func_args.gc_name;
     check_func(&mut item_fn);
 
     let name = &item_fn.sig.ident;
@@ -367,13 +499,17 @@ fn ocaml_bytecode_func_impl(
         let convert_params: Vec<_> = args
             .iter()
             .filter_map(|arg| match arg {
-                Some(ident) => Some(quote! {
-                    #[allow(clippy::not_unsafe_ptr_arg_deref)]
-                    let #ident = ocaml::FromValue::from_value(unsafe {
-                        core::ptr::read(__ocaml_argv.add(__ocaml_arg_index as usize))
-                    });
-                    __ocaml_arg_index += 1 ;
-                }),
+                Some(ident) => {
+                    let convert = convert_param(&ident.ident, func_args);
+                    Some(quote! {
+                        #[allow(clippy::not_unsafe_ptr_arg_deref)]
+                        let #ident = unsafe {
+                            core::ptr::read(__ocaml_argv.add(__ocaml_arg_index as usize))
+                        };
+                        #convert
+                        __ocaml_arg_index += 1 ;
+                    })
+                }
                 None => None,
             })
             .collect();
@@ -399,10 +535,7 @@ fn ocaml_bytecode_func_impl(
         let convert_params: Vec<_> = args
             .iter()
             .filter_map(|arg| match arg {
-                Some(ident) => {
-                    let ident = ident.ident.clone();
-                    Some(quote! { let #ident = ocaml::FromValue::from_value(#ident); })
-                }
+                Some(ident) => Some(convert_param(&ident.ident, func_args)),
                 None => None,
             })
             .collect();
@@ -424,5 +557,64 @@ fn ocaml_bytecode_func_impl(
     }
 }
 
-synstructure::decl_derive!([IntoValue, attributes(ocaml)] => derive::intovalue_derive);
-synstructure::decl_derive!([FromValue, attributes(ocaml)] => derive::fromvalue_derive);
+synstructure::decl_derive!(
+    [IntoValue, attributes(ocaml)] =>
+    /// Derive `ocaml::IntoValue` for structs and enums
+    ///
+    /// Container and variant attributes:
+    ///
+    /// - `#[ocaml(unboxed)]`: a struct with a single field is represented by that field
+    /// - `#[ocaml(floats_array)]`: a struct containing only floats is stored as a float array
+    /// - `#[ocaml(polymorphic)]`: enum variants are encoded as polymorphic variants
+    /// - `#[ocaml(sig = "type")]`: the OCaml type definition generated by `ocaml-build`
+    /// - `#[ocaml(bound = "T: Trait, ...")]`: replaces the inferred bounds on type parameters
+    /// - `#[ocaml(tag = N)]`: the tag of an enum variant. Constant constructors and constructors
+    ///   with arguments are numbered separately, in declaration order like OCaml, and variants
+    ///   without a tag follow the previous variant of the same kind
+    /// - `#[ocaml(discriminants)]`: use the Rust discriminant of each variant (`A = 2`) as its tag,
+    ///   variants without a discriminant are numbered like Rust does, one more than the previous
+    ///   variant of either kind. Discriminants are ignored without this attribute
+    ///
+    /// Each type parameter used by a stored field is bound by the derived trait (`T: IntoValue`),
+    /// `PhantomData` fields are skipped.
+    ///
+    /// Enum variants with named fields map to OCaml inline records (`Foo of { a: int; b: float }`),
+    /// these are stored in a block tagged like any other constructor, even when every field is a
+    /// float, so `floats_array` and `unboxed` can only be used on structs and enums with a single
+    /// variant.
+    ///
+    /// Field attributes:
+    ///
+    /// - `#[ocaml(skip)]`: the field is not stored in the OCaml value
+    /// - `#[ocaml(default)]`/`#[ocaml(default = "expr")]`: the value used by `FromValue` for skipped
+    /// fields or when the block is too short to contain the field
+    /// - `#[ocaml(with = "module")]`: convert the field using `module::into_value(x, gc)` and
+    /// `module::from_value(v)`
+    /// - `#[ocaml(index = N)]`: store the field at index `N`, fields without an index take the next
+    /// free slot
+    derive::intovalue_derive
+);
+synstructure::decl_derive!(
+    [FromValue, attributes(ocaml)] =>
+    /// Derive `ocaml::FromValue` for structs and enums, see `IntoValue` for the supported attributes
+    derive::fromvalue_derive
+);
+synstructure::decl_derive!(
+    [Exception, attributes(ocaml)] =>
+    /// Derive `ocaml::Exception` for error enums, each variant is an OCaml exception registered
+    /// using `Callback.register_exception` under the name of the variant, or the name set using
+    /// `#[ocaml(name = "...")]`. The fields of a variant are the arguments of the exception and
+    /// support the same field attributes as `IntoValue`.
+    ///
+    /// To raise the exception from `#[ocaml::func]` functions return `Result<T, ocaml::Raise<E>>`,
+    /// `ocaml::Raise` can be converted to `ocaml::Error`, so the enum can also implement
+    /// `std::error::Error`
+    derive::exception_derive
+);
+synstructure::decl_derive!(
+    [TryFromValue, attributes(ocaml)] =>
+    /// Derive `ocaml::TryFromValue` for structs and enums, the tag and size of the value are
+    /// checked before converting each field and errors record the path of the failing field. See
+    /// `IntoValue` for the supported attributes, `with` modules must also provide `try_from_value`
+    derive::tryfromvalue_derive
+);
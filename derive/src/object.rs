@@ -0,0 +1,192 @@
+use quote::quote;
+
+/// Name of the OCaml method called for `method`, set using `#[ocaml(name = "...")]`. The attribute
+/// is removed since it isn't valid on trait items
+fn method_name(method: &mut syn::TraitItemMethod) -> String {
+    let mut name = method.sig.ident.to_string();
+    method.attrs.retain(|attr| {
+        if !attr.path.is_ident("ocaml") {
+            return true;
+        }
+        match attr.parse_meta() {
+            Ok(syn::Meta::List(list)) => {
+                for meta in list.nested.iter() {
+                    match meta {
+                        syn::NestedMeta::Meta(syn::Meta::NameValue(nv))
+                            if nv.path.is_ident("name") =>
+                        {
+                            match &nv.lit {
+                                syn::Lit::Str(s) => name = s.value(),
+                                _ => panic!("ocaml method name must be a string"),
+                            }
+                        }
+                        _ => panic!("unexpected ocaml method attribute parameter"),
+                    }
+                }
+            }
+            _ => panic!("ocaml attribute must take a list of valid attributes in parentheses"),
+        }
+        false
+    });
+    name
+}
+
+/// Returns the `T` in `Result<T, E>`
+fn result_ok_type(ty: &syn::Type) -> Option<&syn::Type> {
+    let segment = match ty {
+        syn::Type::Path(p) => p.path.segments.last()?,
+        _ => return None,
+    };
+    if segment.ident != "Result" {
+        return None;
+    }
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) => args.args.iter().find_map(|arg| match arg {
+            syn::GenericArgument::Type(t) => Some(t),
+            _ => None,
+        }),
+        _ => None,
+    }
+}
+
+fn is_unit(ty: &syn::Type) -> bool {
+    matches!(ty, syn::Type::Tuple(t) if t.elems.is_empty())
+}
+
+fn proxy_method(method: &mut syn::TraitItemMethod) -> proc_macro2::TokenStream {
+    let name = method_name(method);
+    let sig = &method.sig;
+    if !sig.generics.params.is_empty() {
+        panic!("OCaml object methods may not contain generics")
+    }
+    match sig.inputs.first() {
+        Some(syn::FnArg::Receiver(r)) if r.reference.is_some() && r.mutability.is_none() => (),
+        _ => panic!("OCaml object methods must take &self"),
+    }
+
+    let args: Vec<_> = sig
+        .inputs
+        .iter()
+        .skip(1)
+        .map(|arg| match arg {
+            syn::FnArg::Typed(t) => match t.pat.as_ref() {
+                syn::Pat::Ident(ident) => &ident.ident,
+                _ => panic!("OCaml object method arguments must be identifiers"),
+            },
+            syn::FnArg::Receiver(_) => unreachable!(),
+        })
+        .collect();
+
+    let missing = format!("OCaml object has no method {}", name);
+    let call = quote! {
+        unsafe {
+            let gc = ocaml::Runtime::recover_handle();
+            match self.0.method(&gc, #name) {
+                Some(method) => {
+                    let args = [self.0, #(ocaml::IntoValue::into_value(#args, &gc)),*];
+                    method.call_n(&gc, args)
+                }
+                None => Err(ocaml::Error::Message(#missing.into())),
+            }
+        }
+    };
+
+    // `()` doesn't implement `FromValue`, so the result of methods returning unit is dropped
+    let body = match &sig.output {
+        syn::ReturnType::Type(_, ty) if result_ok_type(ty).is_some() => {
+            let ok = if result_ok_type(ty).map(is_unit) == Some(true) {
+                quote!(Ok(_) => Ok(()))
+            } else {
+                quote!(Ok(x) => Ok(ocaml::FromValue::from_value(x)))
+            };
+            quote! {
+                let res: Result<ocaml::Value, ocaml::Error> = #call;
+                match res {
+                    #ok,
+                    Err(e) => Err(e.into()),
+                }
+            }
+        }
+        syn::ReturnType::Default => quote! {
+            let res: Result<ocaml::Value, ocaml::Error> = #call;
+            if let Err(e) = res {
+                panic!("{}: {:?}", #name, e)
+            }
+        },
+        syn::ReturnType::Type(_, ty) if is_unit(ty) => quote! {
+            let res: Result<ocaml::Value, ocaml::Error> = #call;
+            if let Err(e) = res {
+                panic!("{}: {:?}", #name, e)
+            }
+        },
+        _ => quote! {
+            let res: Result<ocaml::Value, ocaml::Error> = #call;
+            match res {
+                Ok(x) => ocaml::FromValue::from_value(x),
+                Err(e) => panic!("{}: {:?}", #name, e),
+            }
+        },
+    };
+
+    quote! {
+        #sig {
+            #body
+        }
+    }
+}
+
+pub fn object(
+    attribute: proc_macro::TokenStream,
+    item: syn::ItemTrait,
+) -> proc_macro2::TokenStream {
+    let mut item = item;
+    if !item.generics.params.is_empty() {
+        panic!("OCaml object traits may not contain generics")
+    }
+
+    let proxy = match syn::parse::<syn::Ident>(attribute) {
+        Ok(ident) => ident,
+        Err(_) => syn::Ident::new(&format!("{}Object", item.ident), item.ident.span()),
+    };
+
+    let methods: Vec<_> = item
+        .items
+        .iter_mut()
+        .filter_map(|item| match item {
+            syn::TraitItem::Method(m) => Some(proxy_method(m)),
+            _ => None,
+        })
+        .collect();
+
+    let vis = &item.vis;
+    let trait_name = &item.ident;
+    let doc = format!(
+        "Proxy for an OCaml object with the methods of `{}`",
+        trait_name
+    );
+
+    quote! {
+        #item
+
+        #[doc = #doc]
+        #[derive(Clone, Copy)]
+        #[repr(transparent)]
+        #vis struct #proxy(pub ocaml::Value);
+
+        unsafe impl ocaml::FromValue for #proxy {
+            fn from_value(value: ocaml::Value) -> Self {
+                #proxy(value)
+            }
+        }
+
+        unsafe impl ocaml::IntoValue for #proxy {
+            fn into_value(self, _gc: &ocaml::Runtime) -> ocaml::Value {
+                self.0
+            }
+        }
+
+        impl #trait_name for #proxy {
+            #(#methods)*
+        }
+    }
+}
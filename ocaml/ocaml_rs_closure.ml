@@ -0,0 +1,5 @@
+(* OCaml closures can only point to OCaml code, [Value::closure] calls Rust closures through this
+   trampoline *)
+external call : Obj.t -> 'a -> 'b = "ocaml_rs_closure_call"
+
+let () = Callback.register "ocaml_rs_closure" (fun f x -> call f x)
@@ -0,0 +1,46 @@
+type t = {
+  type_name : string;
+  message : string;
+  causes : string list;
+  backtrace : string option;
+}
+
+exception Rust_error of t
+
+(* Earlier examples registered a [Rust_error] exception with a [string] argument, so the record
+   version is registered under a different name *)
+let () =
+  Callback.register_exception "Rust_error_v2"
+    (Rust_error { type_name = ""; message = ""; causes = []; backtrace = None })
+
+(* Generic arguments can contain paths too, so only strip the path before the first '<' *)
+let type_name e =
+  let name =
+    match String.index_opt e.type_name '<' with
+    | Some i -> String.sub e.type_name 0 i
+    | None -> e.type_name
+  in
+  let rec start i =
+    if i <= 0 then 0
+    else if name.[i - 1] = ':' then i
+    else start (i - 1)
+  in
+  let i = start (String.length name) in
+  String.sub e.type_name i (String.length e.type_name - i)
+
+let is name e = type_name e = name
+
+let to_string e = String.concat ": " (e.message :: e.causes)
+
+let pp fmt e =
+  Format.fprintf fmt "@[<v>%s: %s" e.type_name e.message;
+  List.iter (fun c -> Format.fprintf fmt "@,caused by: %s" c) e.causes;
+  Option.iter (fun bt -> Format.fprintf fmt "@,%s" bt) e.backtrace;
+  Format.fprintf fmt "@]"
+
+let catch f = try Ok (f ()) with Rust_error e -> Error e
+
+let () =
+  Printexc.register_printer (function
+    | Rust_error e -> Some (Printf.sprintf "Rust_error(%s: %s)" (type_name e) (to_string e))
+    | _ -> None)
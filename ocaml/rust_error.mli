@@ -0,0 +1,29 @@
+(** Errors raised by Rust functions using [ocaml-rs]
+
+    Rust errors converted to [ocaml::Error] are raised as [Rust_error] once this module has been
+    linked, otherwise they're raised as [Failure] *)
+
+type t = {
+  type_name : string;  (** Name of the Rust error type *)
+  message : string;  (** [Display] output of the error *)
+  causes : string list;  (** Messages of the [source()] chain, outermost first *)
+  backtrace : string option;
+      (** Rust backtrace, captured when [RUST_BACKTRACE] or [RUST_LIB_BACKTRACE] is set *)
+}
+
+exception Rust_error of t
+
+val type_name : t -> string
+(** Name of the Rust error type without the module path *)
+
+val is : string -> t -> bool
+(** [is name e] returns true when [type_name e] is [name] *)
+
+val to_string : t -> string
+(** The message followed by each cause, separated by [": "] *)
+
+val pp : Format.formatter -> t -> unit
+(** Print the type name, message, causes and backtrace *)
+
+val catch : (unit -> 'a) -> ('a, t) result
+(** Call a function, returning [Error] when it raises [Rust_error] *)
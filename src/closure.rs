@@ -0,0 +1,89 @@
+use core::cell::{Cell, UnsafeCell};
+
+use crate::{Error, FromValue, IntoValue, Pointer, Runtime, Value};
+
+/// Name of the OCaml trampoline used to turn Rust closures into OCaml closures
+const TRAMPOLINE: &str = "ocaml_rs_closure";
+
+/// The callback clears the running flag once the Rust closure returns, before its result is
+/// converted, since converting an `Err` raises an OCaml exception
+type Callback = Box<dyn FnMut(&Runtime, Value, &Cell<bool>) -> Value>;
+
+/// Boxed Rust closure owned by the OCaml garbage collector
+struct Closure {
+    callback: UnsafeCell<Callback>,
+
+    /// Set while the closure is running, calling it again from inside would create a second
+    /// mutable reference to it
+    running: Cell<bool>,
+}
+
+unsafe extern "C" fn closure_finalize(v: Value) {
+    let p: Pointer<Closure> = Pointer::from_value(v);
+    p.drop_in_place()
+}
+
+crate::custom!(Closure {
+    finalize: closure_finalize
+});
+
+/// Called by the OCaml trampoline with the custom block holding the closure and its argument
+#[no_mangle]
+pub unsafe extern "C" fn ocaml_rs_closure_call(f: Value, arg: Value) -> Value {
+    let rt = Runtime::recover_handle();
+    crate::init_panic_handler();
+    let f: Pointer<Closure> = Pointer::from_value(f);
+    let closure = f.as_ref();
+    if closure.running.replace(true) {
+        Error::raise_failure(rt, "Rust closure called while it is already running")
+    }
+    (*closure.callback.get())(rt, arg, &closure.running)
+}
+
+impl Value {
+    /// Convert a Rust closure into an OCaml function value. The closure is stored in a custom block
+    /// and dropped when the OCaml function is garbage collected.
+    ///
+    /// OCaml closures can only point to OCaml code, so the closure is called through a trampoline
+    /// registered by the OCaml program. Linking the `ocaml-rs.closure` library from the `ocaml`
+    /// directory of the `ocaml-rs` repository registers it, or it can be registered directly:
+    ///
+    /// ```ocaml
+    /// external call : Obj.t -> 'a -> 'b = "ocaml_rs_closure_call"
+    /// let () = Callback.register "ocaml_rs_closure" (fun f x -> call f x)
+    /// ```
+    ///
+    /// An error is returned when the trampoline hasn't been registered. Calling the function again
+    /// while the closure is running, or after it panicked, raises `Failure`
+    pub fn closure<A, R, F>(rt: &Runtime, mut f: F) -> Result<Value, Error>
+    where
+        A: FromValue,
+        R: IntoValue,
+        F: 'static + FnMut(A) -> R,
+    {
+        if unsafe { Value::named::<Value>(TRAMPOLINE) }.is_none() {
+            return Err(Error::Message(
+                "ocaml_rs_closure has not been registered with the OCaml runtime, link the \
+                 ocaml-rs.closure library"
+                    .into(),
+            ));
+        }
+
+        let callback: Callback = Box::new(move |rt, arg, running| {
+            let ret = f(A::from_value(arg));
+            running.set(false);
+            ret.into_value(rt)
+        });
+        let closure = Closure {
+            callback: UnsafeCell::new(callback),
+            running: Cell::new(false),
+        };
+        let data = Pointer::alloc_custom(rt, closure);
+
+        // The trampoline is looked up again since allocating may have moved it
+        unsafe {
+            let trampoline: Value = Value::named(TRAMPOLINE).unwrap();
+            trampoline.call(rt, data)
+        }
+    }
+}
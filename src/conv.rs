@@ -1,4 +1,5 @@
 use crate::{
+    util::Sealed,
     value::{FromValue, IntoValue, Value},
     Runtime, Tag,
 };
@@ -28,12 +29,20 @@ macro_rules! value_f {
             fn into_value(self, rt: &Runtime) -> $crate::Value {
                 unsafe { $crate::Value::float(rt, self as crate::Float) }
             }
+
+            fn into_float(_: Sealed) -> Option<fn($t) -> f64> {
+                Some(|x| x as f64)
+            }
         }
 
         unsafe impl FromValue for $t {
             fn from_value(v: $crate::Value) -> $t {
                 unsafe { v.float_val() as $t }
             }
+
+            fn from_float(_: Sealed) -> Option<fn(f64) -> $t> {
+                Some(|f| f as $t)
+            }
         }
     };
     ($($t:ty),*) => {
@@ -309,6 +318,10 @@ unsafe impl<'a> IntoValue for &mut [u8] {
 #[cfg(not(feature = "no-std"))]
 unsafe impl<'a, V: IntoValue> IntoValue for Vec<V> {
     fn into_value(self, rt: &Runtime) -> Value {
+        if let Some(into_float) = V::into_float(Sealed(())) {
+            return float_array(rt, self.into_iter().map(into_float));
+        }
+
         let len = self.len();
         let mut arr = unsafe { Value::alloc(rt, len, Tag(0)) };
 
@@ -325,6 +338,13 @@ unsafe impl<'a, V: IntoValue> IntoValue for Vec<V> {
 #[cfg(not(feature = "no-std"))]
 unsafe impl<'a, V: FromValue> FromValue for Vec<V> {
     fn from_value(v: Value) -> Vec<V> {
+        if let Some(from_float) = V::from_float(Sealed(())) {
+            return crate::FloatArray::from_value(v)
+                .iter()
+                .map(from_float)
+                .collect();
+        }
+
         unsafe {
             let len = crate::sys::caml_array_length(v.0);
             let mut dst = Vec::with_capacity(len);
@@ -336,6 +356,47 @@ unsafe impl<'a, V: FromValue> FromValue for Vec<V> {
     }
 }
 
+#[cfg(not(feature = "no-std"))]
+unsafe impl<V: IntoValue> IntoValue for Box<[V]> {
+    fn into_value(self, rt: &Runtime) -> Value {
+        self.into_vec().into_value(rt)
+    }
+}
+
+#[cfg(not(feature = "no-std"))]
+unsafe impl<V: FromValue> FromValue for Box<[V]> {
+    fn from_value(v: Value) -> Box<[V]> {
+        Vec::from_value(v).into_boxed_slice()
+    }
+}
+
+/// Allocate an OCaml `float array`, the elements are boxed when OCaml has been configured with
+/// `-no-flat-float-array`
+fn float_array<I: ExactSizeIterator<Item = f64>>(rt: &Runtime, data: I) -> Value {
+    frame!(rt: (arr) {
+        unsafe {
+            arr.0 = crate::sys::caml_alloc_float_array(data.len());
+            if arr.tag() == Tag::DOUBLE_ARRAY {
+                let ptr = arr.0 as *mut f64;
+                for (i, f) in data.enumerate() {
+                    *ptr.add(i) = f;
+                }
+            } else {
+                for (i, f) in data.enumerate() {
+                    arr.store_field(rt, i, f);
+                }
+            }
+            arr
+        }
+    })
+}
+
+unsafe impl IntoValue for &[f64] {
+    fn into_value(self, rt: &Runtime) -> Value {
+        float_array(rt, self.iter().copied())
+    }
+}
+
 unsafe impl<'a> FromValue for &'a [Value] {
     fn from_value(value: Value) -> &'a [Value] {
         unsafe {
@@ -0,0 +1,555 @@
+//! Checked conversion from OCaml values, these conversions validate the shape of a value before
+//! reading it and report where a malformed value was found
+
+use crate::util::Sealed;
+use crate::{bigarray, sys, Array, FloatArray, List, Tag, Value};
+
+use std::ffi::CStr;
+
+/// A single step in the path to a value that failed to decode
+#[derive(Clone, Debug, PartialEq)]
+pub enum PathSegment {
+    /// Named (or positional) field of a record, tuple or constructor
+    Field(&'static str),
+
+    /// Index into an array or list
+    Index(usize),
+}
+
+/// Error returned by `TryFromValue` when a value doesn't have the expected shape
+#[derive(Clone, PartialEq)]
+pub struct DecodeError {
+    /// Path segments, stored from innermost to outermost
+    path: Vec<PathSegment>,
+    expected: String,
+    got: String,
+}
+
+impl DecodeError {
+    /// Create a new error, describing the value that was found
+    pub fn new<S: Into<String>>(expected: S, value: Value) -> DecodeError {
+        DecodeError {
+            path: Vec::new(),
+            expected: expected.into(),
+            got: unsafe { describe(value) },
+        }
+    }
+
+    /// Add a field to the front of the error path
+    pub fn field(mut self, name: &'static str) -> DecodeError {
+        self.path.push(PathSegment::Field(name));
+        self
+    }
+
+    /// Add an index to the front of the error path
+    pub fn index(mut self, i: usize) -> DecodeError {
+        self.path.push(PathSegment::Index(i));
+        self
+    }
+
+    /// Path to the value that failed to decode, from outermost to innermost
+    pub fn path(&self) -> impl Iterator<Item = &PathSegment> {
+        self.path.iter().rev()
+    }
+
+    /// Description of the expected value
+    pub fn expected(&self) -> &str {
+        &self.expected
+    }
+
+    /// Description of the value that was found
+    pub fn got(&self) -> &str {
+        &self.got
+    }
+
+    /// Ensure `value` is an immediate integer
+    pub fn check_int(value: Value) -> Result<(), DecodeError> {
+        if unsafe { value.is_long() } {
+            Ok(())
+        } else {
+            Err(DecodeError::new("int", value))
+        }
+    }
+
+    /// Ensure `value` is a block with the given tag, of any size
+    pub fn check_tag(value: Value, tag: Tag) -> Result<(), DecodeError> {
+        if unsafe { value.is_block() && value.tag() == tag } {
+            return Ok(());
+        }
+
+        Err(DecodeError::new(format!("block tag {}", tag.0), value))
+    }
+
+    /// Ensure `value` is a block with the given tag and exactly `size` fields
+    pub fn check_block(value: Value, tag: Tag, size: usize) -> Result<(), DecodeError> {
+        DecodeError::check_block_range(value, tag, size, size)
+    }
+
+    /// Ensure `value` is a block with the given tag and between `min` and `max` fields, this is
+    /// used for records whose trailing fields have defaults, so blocks created for an older
+    /// version of the type without those fields are accepted
+    pub fn check_block_range(
+        value: Value,
+        tag: Tag,
+        min: usize,
+        max: usize,
+    ) -> Result<(), DecodeError> {
+        unsafe {
+            if value.is_block() && value.tag() == tag {
+                let size = sys::wosize_val(value.0);
+                if size >= min && size <= max {
+                    return Ok(());
+                }
+            }
+        }
+
+        let expected = if min == max {
+            format!("block tag {} of size {}", tag.0, max)
+        } else {
+            format!("block tag {} of size {} to {}", tag.0, min, max)
+        };
+        Err(DecodeError::new(expected, value))
+    }
+
+    /// Ensure `value` is a custom block with the given identifier
+    pub fn check_custom(value: Value, identifier: &str) -> Result<(), DecodeError> {
+        match unsafe { custom_identifier(value) } {
+            Some(id) if id == identifier => Ok(()),
+            _ => Err(DecodeError::new(
+                format!("custom block {}", identifier),
+                value,
+            )),
+        }
+    }
+}
+
+impl core::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        if !self.path.is_empty() {
+            write!(f, "field ")?;
+            for segment in self.path() {
+                match segment {
+                    PathSegment::Field(name) => write!(f, ".{}", name)?,
+                    PathSegment::Index(i) => write!(f, "[{}]", i)?,
+                }
+            }
+            write!(f, ": ")?;
+        }
+        write!(f, "expected {}, got {}", self.expected, self.got)
+    }
+}
+
+impl core::fmt::Debug for DecodeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "DecodeError({})", self)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+unsafe fn custom_identifier<'a>(value: Value) -> Option<&'a str> {
+    if !value.is_block() || value.tag() != Tag::CUSTOM {
+        return None;
+    }
+
+    let ops = *(value.0 as *const *const sys::custom_operations);
+    if ops.is_null() || (*ops).identifier.is_null() {
+        return None;
+    }
+
+    CStr::from_ptr((*ops).identifier).to_str().ok()
+}
+
+/// Describe the runtime representation of a value, used for error messages
+unsafe fn describe(value: Value) -> String {
+    if value.is_long() {
+        return "int".into();
+    }
+
+    match value.tag() {
+        Tag::STRING => "string".into(),
+        Tag::DOUBLE => "float".into(),
+        Tag::DOUBLE_ARRAY => "float array".into(),
+        Tag::CLOSURE => "closure".into(),
+        Tag::OBJECT => "object".into(),
+        Tag::CUSTOM => match custom_identifier(value) {
+            Some(id) => format!("custom block {}", id),
+            None => "custom block".into(),
+        },
+        tag => format!("block tag {} of size {}", tag.0, sys::wosize_val(value.0)),
+    }
+}
+
+/// `TryFromValue` is used to convert from OCaml values to Rust types, validating the
+/// representation of the value instead of assuming it matches the Rust type
+pub unsafe trait TryFromValue: Sized {
+    #[doc(hidden)]
+    /// Set for types stored unboxed in OCaml float arrays
+    const FLOAT: bool = false;
+
+    /// Convert from OCaml value
+    fn try_from_value(v: Value) -> Result<Self, DecodeError>;
+
+    #[doc(hidden)]
+    /// Conversion from the unboxed representation used by OCaml float arrays, only `f32` and
+    /// `f64` return `Some`
+    fn from_float(_: Sealed) -> Option<fn(f64) -> Self> {
+        None
+    }
+}
+
+unsafe impl TryFromValue for Value {
+    fn try_from_value(v: Value) -> Result<Value, DecodeError> {
+        Ok(v)
+    }
+}
+
+macro_rules! try_value_i {
+    ($($t:ty),*) => {
+        $(
+            unsafe impl TryFromValue for $t {
+                fn try_from_value(v: Value) -> Result<$t, DecodeError> {
+                    DecodeError::check_int(v)?;
+                    use core::convert::TryFrom;
+                    <$t>::try_from(unsafe { v.int_val() })
+                        .map_err(|_| DecodeError::new(concat!("int in the range of ", stringify!($t)), v))
+                }
+            }
+        )*
+    }
+}
+
+try_value_i!(i8, u8, i16, u16, crate::Int, crate::Uint);
+
+macro_rules! try_value_f {
+    ($($t:ty),*) => {
+        $(
+            unsafe impl TryFromValue for $t {
+                const FLOAT: bool = true;
+
+                fn try_from_value(v: Value) -> Result<$t, DecodeError> {
+                    unsafe {
+                        if v.is_block() && v.tag() == Tag::DOUBLE {
+                            return Ok(v.float_val() as $t);
+                        }
+                    }
+                    Err(DecodeError::new("float", v))
+                }
+
+                fn from_float(_: Sealed) -> Option<fn(f64) -> $t> {
+                    Some(|f| f as $t)
+                }
+            }
+        )*
+    }
+}
+
+try_value_f!(f32, f64);
+
+macro_rules! try_value_custom {
+    ($($t:ty: $id:expr => $f:ident),*) => {
+        $(
+            unsafe impl TryFromValue for $t {
+                fn try_from_value(v: Value) -> Result<$t, DecodeError> {
+                    DecodeError::check_custom(v, $id)?;
+                    Ok(unsafe { v.$f() as $t })
+                }
+            }
+        )*
+    }
+}
+
+try_value_custom!(
+    i32: "_i" => int32_val,
+    u32: "_i" => int32_val,
+    i64: "_j" => int64_val,
+    u64: "_j" => int64_val
+);
+
+unsafe impl TryFromValue for bool {
+    fn try_from_value(v: Value) -> Result<bool, DecodeError> {
+        match unsafe { v.is_long().then(|| v.int_val()) } {
+            Some(0) => Ok(false),
+            Some(1) => Ok(true),
+            _ => Err(DecodeError::new("bool", v)),
+        }
+    }
+}
+
+unsafe impl TryFromValue for () {
+    fn try_from_value(v: Value) -> Result<(), DecodeError> {
+        if v == Value::unit() {
+            Ok(())
+        } else {
+            Err(DecodeError::new("unit", v))
+        }
+    }
+}
+
+unsafe impl<'a> TryFromValue for &'a [u8] {
+    fn try_from_value(v: Value) -> Result<&'a [u8], DecodeError> {
+        DecodeError::check_tag(v, Tag::STRING)?;
+        Ok(crate::FromValue::from_value(v))
+    }
+}
+
+unsafe impl<'a> TryFromValue for &'a str {
+    fn try_from_value(v: Value) -> Result<&'a str, DecodeError> {
+        let bytes: &[u8] = TryFromValue::try_from_value(v)?;
+        core::str::from_utf8(bytes).map_err(|_| DecodeError::new("UTF-8 string", v))
+    }
+}
+
+unsafe impl TryFromValue for String {
+    fn try_from_value(v: Value) -> Result<String, DecodeError> {
+        <&str>::try_from_value(v).map(String::from)
+    }
+}
+
+unsafe impl<T: TryFromValue> TryFromValue for Option<T> {
+    fn try_from_value(v: Value) -> Result<Option<T>, DecodeError> {
+        if v == Value::none() {
+            return Ok(None);
+        }
+
+        DecodeError::check_block(v, Tag(sys::TAG_SOME), 1)?;
+        T::try_from_value(unsafe { v.field(0) }).map(Some)
+    }
+}
+
+/// Checks that `v` is an array that can be read as a sequence of `T`
+fn check_array<T: TryFromValue>(v: Value) -> Result<(), DecodeError> {
+    if T::FLOAT && unsafe { v.is_block() && v.tag() == Tag::DOUBLE_ARRAY } {
+        return Ok(());
+    }
+
+    DecodeError::check_tag(v, Tag(0))?;
+    for i in 0..unsafe { sys::wosize_val(v.0) } {
+        T::try_from_value(unsafe { v.field(i) }).map_err(|e| e.index(i))?;
+    }
+
+    Ok(())
+}
+
+/// Decodes each element of the list `v`, stopping at the first error
+fn decode_list<T: TryFromValue>(v: Value, mut f: impl FnMut(T)) -> Result<(), DecodeError> {
+    let mut tmp = v;
+    let mut i = 0;
+    while tmp.0 != sys::EMPTY_LIST {
+        DecodeError::check_block(tmp, Tag(sys::TAG_CONS), 2).map_err(|e| e.index(i))?;
+        f(T::try_from_value(unsafe { tmp.field(0) }).map_err(|e| e.index(i))?);
+        tmp = unsafe { tmp.field(1) };
+        i += 1;
+    }
+
+    Ok(())
+}
+
+unsafe impl<T: TryFromValue> TryFromValue for Vec<T> {
+    fn try_from_value(v: Value) -> Result<Vec<T>, DecodeError> {
+        if let Some(from_float) = T::from_float(Sealed(())) {
+            if unsafe { v.is_block() && v.tag() == Tag::DOUBLE_ARRAY } {
+                let arr: FloatArray = crate::FromValue::from_value(v);
+                return Ok(arr.iter().map(from_float).collect());
+            }
+        }
+
+        DecodeError::check_tag(v, Tag(0))?;
+        let len = unsafe { sys::wosize_val(v.0) };
+        let mut dst = Vec::with_capacity(len);
+        for i in 0..len {
+            dst.push(T::try_from_value(unsafe { v.field(i) }).map_err(|e| e.index(i))?);
+        }
+        Ok(dst)
+    }
+}
+
+unsafe impl<T: TryFromValue> TryFromValue for Box<[T]> {
+    fn try_from_value(v: Value) -> Result<Box<[T]>, DecodeError> {
+        Vec::try_from_value(v).map(Vec::into_boxed_slice)
+    }
+}
+
+unsafe impl<'a> TryFromValue for FloatArray<'a> {
+    fn try_from_value(v: Value) -> Result<FloatArray<'a>, DecodeError> {
+        check_array::<f64>(v)?;
+        Ok(crate::FromValue::from_value(v))
+    }
+}
+
+unsafe impl<'a> TryFromValue for &'a [f64] {
+    fn try_from_value(v: Value) -> Result<&'a [f64], DecodeError> {
+        FloatArray::try_from_value(v)?
+            .as_slice()
+            .ok_or_else(|| DecodeError::new("flat float array", v))
+    }
+}
+
+unsafe impl<T: TryFromValue + crate::IntoValue + crate::FromValue> TryFromValue for Array<T> {
+    fn try_from_value(v: Value) -> Result<Array<T>, DecodeError> {
+        check_array::<T>(v)?;
+        Ok(crate::FromValue::from_value(v))
+    }
+}
+
+unsafe impl<T: TryFromValue + crate::IntoValue + crate::FromValue> TryFromValue for List<T> {
+    fn try_from_value(v: Value) -> Result<List<T>, DecodeError> {
+        decode_list::<T>(v, drop)?;
+        Ok(crate::FromValue::from_value(v))
+    }
+}
+
+unsafe impl<T: TryFromValue> TryFromValue for std::collections::LinkedList<T> {
+    fn try_from_value(v: Value) -> Result<std::collections::LinkedList<T>, DecodeError> {
+        let mut dest = std::collections::LinkedList::new();
+        decode_list(v, |x| dest.push_back(x))?;
+        Ok(dest)
+    }
+}
+
+unsafe impl<K: Ord + TryFromValue, V: TryFromValue> TryFromValue
+    for std::collections::BTreeMap<K, V>
+{
+    fn try_from_value(v: Value) -> Result<std::collections::BTreeMap<K, V>, DecodeError> {
+        let mut dest = std::collections::BTreeMap::new();
+        decode_list(v, |(k, v)| {
+            dest.insert(k, v);
+        })?;
+        Ok(dest)
+    }
+}
+
+macro_rules! try_closure_impl {
+    ($name:ident($($t:ident),*)) => {
+        unsafe impl<$($t,)* R> TryFromValue for crate::$name<$($t,)* R> {
+            fn try_from_value(v: Value) -> Result<crate::$name<$($t,)* R>, DecodeError> {
+                if unsafe { v.is_block() && v.tag() == Tag::CLOSURE } {
+                    Ok(crate::FromValue::from_value(v))
+                } else {
+                    Err(DecodeError::new("closure", v))
+                }
+            }
+        }
+    };
+}
+
+try_closure_impl!(Fn1(A));
+try_closure_impl!(Fn2(A, B));
+try_closure_impl!(Fn3(A, B, C));
+try_closure_impl!(Fn4(A, B, C, D));
+try_closure_impl!(Fn5(A, B, C, D, E));
+
+macro_rules! try_tuple_impl {
+    ($($t:ident: $n:tt),*) => {
+        unsafe impl<$($t: TryFromValue),*> TryFromValue for ($($t,)*) {
+            fn try_from_value(v: Value) -> Result<($($t,)*), DecodeError> {
+                let len = [$($n),*].len();
+                DecodeError::check_block(v, Tag(0), len)?;
+                Ok((
+                    $(
+                        $t::try_from_value(unsafe { v.field($n) })
+                            .map_err(|e| e.field(stringify!($n)))?,
+                    )*
+                ))
+            }
+        }
+    };
+}
+
+try_tuple_impl!(A: 0);
+try_tuple_impl!(A: 0, B: 1);
+try_tuple_impl!(A: 0, B: 1, C: 2);
+try_tuple_impl!(A: 0, B: 1, C: 2, D: 3);
+try_tuple_impl!(A: 0, B: 1, C: 2, D: 3, E: 4);
+try_tuple_impl!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+try_tuple_impl!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6);
+try_tuple_impl!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7);
+try_tuple_impl!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8);
+try_tuple_impl!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9);
+try_tuple_impl!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9, K: 10);
+try_tuple_impl!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9, K: 10, L: 11);
+
+fn kind_name(kind: i32) -> &'static str {
+    use sys::bigarray::Kind::*;
+    const KINDS: [(i32, &str); 13] = [
+        (FLOAT32 as i32, "float32"),
+        (FLOAT64 as i32, "float64"),
+        (SINT8 as i32, "int8_signed"),
+        (UINT8 as i32, "int8_unsigned"),
+        (SINT16 as i32, "int16_signed"),
+        (UINT16 as i32, "int16_unsigned"),
+        (INT32 as i32, "int32"),
+        (INT64 as i32, "int64"),
+        (CAML_INT as i32, "int"),
+        (NATIVE_INT as i32, "nativeint"),
+        (COMPLEX32 as i32, "complex32"),
+        (COMPLEX64 as i32, "complex64"),
+        (CHAR as i32, "char"),
+    ];
+    KINDS
+        .iter()
+        .find(|(k, _)| *k == kind)
+        .map_or("unknown", |(_, name)| name)
+}
+
+/// Checks that `v` is a bigarray with the element kind of `T` and `dims` dimensions, any number of
+/// dimensions is accepted when `dims` is `None`
+pub(crate) fn check_bigarray<T: bigarray::Kind>(
+    v: Value,
+    dims: Option<usize>,
+) -> Result<(), DecodeError> {
+    match unsafe { custom_identifier(v) } {
+        Some(id) if id.starts_with("_bigarr") => (),
+        _ => return Err(DecodeError::new("bigarray", v)),
+    }
+
+    let ba = unsafe { &*v.custom_ptr_val::<sys::bigarray::Bigarray>() };
+    let kind = (ba.flags & sys::bigarray::Kind::KIND_MASK as sys::Intnat) as i32;
+    if kind != T::kind() || matches!(dims, Some(dims) if ba.num_dims as usize != dims) {
+        let expected = match dims {
+            Some(dims) => format!("{} bigarray with {} dimensions", kind_name(T::kind()), dims),
+            None => format!("{} bigarray", kind_name(T::kind())),
+        };
+        return Err(DecodeError {
+            path: Vec::new(),
+            expected,
+            got: format!(
+                "{} bigarray with {} dimensions",
+                kind_name(kind),
+                ba.num_dims
+            ),
+        });
+    }
+
+    Ok(())
+}
+
+unsafe impl<T: bigarray::Kind> TryFromValue for bigarray::Array1<T> {
+    fn try_from_value(v: Value) -> Result<bigarray::Array1<T>, DecodeError> {
+        check_bigarray::<T>(v, Some(1))?;
+        Ok(crate::FromValue::from_value(v))
+    }
+}
+
+unsafe impl<T: bigarray::Kind> TryFromValue for bigarray::ArrayN<T> {
+    fn try_from_value(v: Value) -> Result<bigarray::ArrayN<T>, DecodeError> {
+        check_bigarray::<T>(v, None)?;
+        Ok(crate::FromValue::from_value(v))
+    }
+}
+
+#[cfg(feature = "bigarray-ext")]
+unsafe impl<T: bigarray::Kind> TryFromValue for bigarray::Array2<T> {
+    fn try_from_value(v: Value) -> Result<bigarray::Array2<T>, DecodeError> {
+        check_bigarray::<T>(v, Some(2))?;
+        Ok(crate::FromValue::from_value(v))
+    }
+}
+
+#[cfg(feature = "bigarray-ext")]
+unsafe impl<T: bigarray::Kind> TryFromValue for bigarray::Array3<T> {
+    fn try_from_value(v: Value) -> Result<bigarray::Array3<T>, DecodeError> {
+        check_bigarray::<T>(v, Some(3))?;
+        Ok(crate::FromValue::from_value(v))
+    }
+}
@@ -1,4 +1,5 @@
-use crate::{FromValue, IntoValue, Runtime, Value};
+use crate::util::{Cow, String};
+use crate::{FromValue, IntoValue, Runtime, Tag, Value};
 
 /// Errors that are translated directly into OCaml exceptions
 #[derive(Debug)]
@@ -7,10 +8,10 @@ pub enum CamlError {
     NotFound,
 
     /// Failure
-    Failure(&'static str),
+    Failure(Cow<'static, str>),
 
     /// Invalid_argument
-    InvalidArgument(&'static str),
+    InvalidArgument(Cow<'static, str>),
 
     /// Out_of_memory
     OutOfMemory,
@@ -19,7 +20,7 @@ pub enum CamlError {
     StackOverflow,
 
     /// Sys_error
-    SysError(&'static str),
+    SysError(Cow<'static, str>),
 
     /// End_of_file
     EndOfFile,
@@ -33,6 +34,19 @@ pub enum CamlError {
     /// Sys_blocked_io
     SysBlockedIo,
 
+    /// Exit, this is defined by the standard library rather than the runtime, so it has to be
+    /// registered using `Callback.register_exception "Exit" Exit` before it can be raised
+    Exit,
+
+    /// Match_failure, the file name, line and column
+    MatchFailure(Cow<'static, str>, crate::Int, crate::Int),
+
+    /// Assert_failure, the file name, line and column
+    AssertFailure(Cow<'static, str>, crate::Int, crate::Int),
+
+    /// Undefined_recursive_module, the file name, line and column
+    UndefinedRecursiveModule(Cow<'static, str>, crate::Int, crate::Int),
+
     /// A pre-allocated OCaml exception
     Exception(Value),
 
@@ -40,6 +54,320 @@ pub enum CamlError {
     WithArg(Value, Value),
 }
 
+impl CamlError {
+    /// Convert an OCaml exception value, predefined exceptions are converted to the matching
+    /// variant and any other exception is returned as `CamlError::Exception`
+    pub fn from_exception(exn: Value) -> CamlError {
+        let exn = Exn(exn);
+
+        // `Exit` is defined in the standard library, so it isn't a predefined exception
+        if exn.is("Stdlib.Exit") || exn.is("Pervasives.Exit") {
+            return CamlError::Exit;
+        }
+
+        if !exn.is_predefined() {
+            return CamlError::Exception(exn.0);
+        }
+
+        let string = || Cow::Owned(String::from(exn.arg::<&str>(0).unwrap_or_default()));
+        let location = || {
+            let (file, line, col): (&str, crate::Int, crate::Int) = exn.arg(0).unwrap();
+            (Cow::Owned(String::from(file)), line, col)
+        };
+
+        match (exn.name(), exn.arity()) {
+            ("Not_found", 0) => CamlError::NotFound,
+            ("Out_of_memory", 0) => CamlError::OutOfMemory,
+            ("Stack_overflow", 0) => CamlError::StackOverflow,
+            ("End_of_file", 0) => CamlError::EndOfFile,
+            ("Division_by_zero", 0) => CamlError::ZeroDivide,
+            ("Sys_blocked_io", 0) => CamlError::SysBlockedIo,
+            ("Failure", 1) => CamlError::Failure(string()),
+            ("Invalid_argument", 1) => CamlError::InvalidArgument(string()),
+            ("Sys_error", 1) => CamlError::SysError(string()),
+            ("Match_failure", 1) => {
+                let (file, line, col) = location();
+                CamlError::MatchFailure(file, line, col)
+            }
+            ("Assert_failure", 1) => {
+                let (file, line, col) = location();
+                CamlError::AssertFailure(file, line, col)
+            }
+            ("Undefined_recursive_module", 1) => {
+                let (file, line, col) = location();
+                CamlError::UndefinedRecursiveModule(file, line, col)
+            }
+            _ => CamlError::Exception(exn.0),
+        }
+    }
+
+    /// Allocate the OCaml exception for a variant, `None` is returned for `Exception` and
+    /// `WithArg`, or when the exception constructor can't be found
+    pub(crate) fn to_exception(&self, rt: &Runtime) -> Option<Value> {
+        let string = |s: &Cow<'static, str>| Some(s.as_ref().into_value(rt));
+        let (name, arg) = match self {
+            CamlError::NotFound => ("Not_found", None),
+            CamlError::OutOfMemory => ("Out_of_memory", None),
+            CamlError::StackOverflow => ("Stack_overflow", None),
+            CamlError::EndOfFile => ("End_of_file", None),
+            CamlError::ZeroDivide => ("Division_by_zero", None),
+            CamlError::SysBlockedIo => ("Sys_blocked_io", None),
+            CamlError::Exit => ("Exit", None),
+            CamlError::Failure(s) => ("Failure", string(s)),
+            CamlError::InvalidArgument(s) => ("Invalid_argument", string(s)),
+            CamlError::SysError(s) => ("Sys_error", string(s)),
+            CamlError::ArrayBoundError => {
+                ("Invalid_argument", string(&"index out of bounds".into()))
+            }
+            CamlError::MatchFailure(file, line, col) => (
+                "Match_failure",
+                Some(location(rt, file.clone(), *line, *col)),
+            ),
+            CamlError::AssertFailure(file, line, col) => (
+                "Assert_failure",
+                Some(location(rt, file.clone(), *line, *col)),
+            ),
+            CamlError::UndefinedRecursiveModule(file, line, col) => (
+                "Undefined_recursive_module",
+                Some(location(rt, file.clone(), *line, *col)),
+            ),
+            CamlError::Exception(_) | CamlError::WithArg(_, _) => return None,
+        };
+
+        let constructor = predefined(name)?;
+        let exn = match arg {
+            None => constructor,
+            // The constructor and argument are rooted since allocating the block may move them
+            Some(a) => unsafe {
+                crate::frame!(rt: (c, x) {
+                    c = constructor;
+                    x = a;
+                    let mut exn = Value::alloc(rt, 2, Tag(0));
+                    exn.store_field(rt, 0, c);
+                    exn.store_field(rt, 1, x);
+                    exn
+                })
+            },
+        };
+        Some(exn)
+    }
+}
+
+/// Predefined exceptions in the order they're stored in `caml_global_data` by the bytecode
+/// runtime, along with the symbol native programs define for each one
+#[cfg(unix)]
+const PREDEFINED: [(&str, &[u8]); 12] = [
+    ("Out_of_memory", b"caml_exn_Out_of_memory\0"),
+    ("Sys_error", b"caml_exn_Sys_error\0"),
+    ("Failure", b"caml_exn_Failure\0"),
+    ("Invalid_argument", b"caml_exn_Invalid_argument\0"),
+    ("End_of_file", b"caml_exn_End_of_file\0"),
+    ("Division_by_zero", b"caml_exn_Division_by_zero\0"),
+    ("Not_found", b"caml_exn_Not_found\0"),
+    ("Match_failure", b"caml_exn_Match_failure\0"),
+    ("Stack_overflow", b"caml_exn_Stack_overflow\0"),
+    ("Sys_blocked_io", b"caml_exn_Sys_blocked_io\0"),
+    ("Assert_failure", b"caml_exn_Assert_failure\0"),
+    (
+        "Undefined_recursive_module",
+        b"caml_exn_Undefined_recursive_module\0",
+    ),
+];
+
+/// Get the constructor of a predefined exception from the OCaml runtime, `Exit` is defined by the
+/// standard library so it's only available after being registered using
+/// `Callback.register_exception "Exit" Exit`
+fn predefined(name: &str) -> Option<Value> {
+    if name == "Exit" {
+        return Error::named("Exit");
+    }
+
+    #[cfg(unix)]
+    unsafe {
+        let index = PREDEFINED.iter().position(|(n, _)| *n == name)?;
+        let symbol = PREDEFINED[index].1.as_ptr() as *const libc::c_char;
+
+        // Native programs define a symbol pointing to each exception
+        let exn = libc::dlsym(libc::RTLD_DEFAULT, symbol);
+        if !exn.is_null() {
+            return Some(Value::new(exn as crate::sys::Value));
+        }
+
+        // The bytecode runtime stores them at the start of the global data
+        let global_data = libc::dlsym(
+            libc::RTLD_DEFAULT,
+            b"caml_global_data\0".as_ptr() as *const libc::c_char,
+        ) as *const crate::sys::Value;
+        if global_data.is_null() || *global_data == 0 || !crate::sys::is_block(*global_data) {
+            return None;
+        }
+        Some(Value::new(*crate::sys::field(*global_data, index)))
+    }
+
+    #[cfg(not(unix))]
+    None
+}
+
+/// `Exn` wraps an OCaml exception value so it can be inspected without converting it, for
+/// example to check which exception was raised by an OCaml callback
+///
+/// ```rust,no_run
+/// # unsafe fn f(gc: &ocaml::Runtime, f: ocaml::Value) {
+/// use ocaml::Exception;
+///
+/// if let Err(e) = f.call(gc, ocaml::Value::unit()) {
+///     if let Some(exn) = ocaml::Exn::from_error(&e) {
+///         println!("{}: {}", exn.name(), exn);
+///     }
+/// }
+/// # }
+/// ```
+///
+/// Predefined exceptions are converted to `CamlError` variants by `Value::call` and
+/// `FromValue for Result<T, Error>`, `Exn::from_error` allocates a new exception for the variant
+/// using the constructor from the OCaml runtime
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(transparent)]
+pub struct Exn(Value);
+
+unsafe impl IntoValue for Exn {
+    fn into_value(self, _rt: &Runtime) -> Value {
+        self.0
+    }
+}
+
+unsafe impl FromValue for Exn {
+    fn from_value(value: Value) -> Exn {
+        Exn(value)
+    }
+}
+
+unsafe impl Exception for Exn {
+    fn into_exception(self, _rt: &Runtime) -> Result<Value, Error> {
+        Ok(self.0)
+    }
+
+    fn from_exception(exn: Value) -> Option<Exn> {
+        Some(Exn(exn))
+    }
+
+    /// Predefined exceptions are allocated from the `CamlError` variant, `None` is returned for
+    /// `CamlError::Exit` when `Exit` hasn't been registered
+    fn from_error(err: &Error) -> Option<Exn> {
+        match err {
+            Error::Caml(CamlError::Exception(exn)) => Some(Exn(*exn)),
+            Error::Caml(err) => {
+                let rt = unsafe { Runtime::recover_handle() };
+                err.to_exception(rt).map(Exn)
+            }
+            #[cfg(not(feature = "no-std"))]
+            Error::WithBacktrace(err, _) => Exn::from_error(err),
+            _ => None,
+        }
+    }
+}
+
+impl Exn {
+    /// Returns true for exceptions without arguments, these are represented by the constructor
+    fn is_constant(&self) -> bool {
+        unsafe { self.0.tag() == Tag::OBJECT }
+    }
+
+    /// The exception constructor, this is the value registered by `Callback.register_exception`
+    pub fn constructor(&self) -> Value {
+        if self.is_constant() {
+            self.0
+        } else {
+            unsafe { self.0.field(0) }
+        }
+    }
+
+    /// The name of the exception constructor, exceptions defined outside of the predefined
+    /// exceptions are prefixed with their module path, for example `Stdlib.Exit`
+    pub fn name(&self) -> &str {
+        unsafe { self.constructor().field(0) }
+    }
+
+    /// Returns true when the exception constructor is named `name`
+    pub fn is(&self, name: &str) -> bool {
+        self.name() == name
+    }
+
+    /// Returns true for exceptions that are built into the OCaml runtime, such as `Not_found` or
+    /// `Failure`
+    pub fn is_predefined(&self) -> bool {
+        // Predefined exceptions have negative ids
+        unsafe { self.constructor().field::<crate::Int>(1) < 0 }
+    }
+
+    /// Number of arguments, an exception with a tuple argument has one argument for each element
+    pub fn arity(&self) -> usize {
+        self.args().len()
+    }
+
+    /// The exception arguments
+    pub fn args(&self) -> &[Value] {
+        if self.is_constant() {
+            return &[];
+        }
+
+        let args: &[Value] = FromValue::from_value(self.0);
+        &args[1..]
+    }
+
+    /// Convert argument `i`, `None` is returned when there is no argument at that index
+    pub fn arg<T: FromValue>(&self, i: usize) -> Option<T> {
+        self.args().get(i).map(|x| T::from_value(*x))
+    }
+
+    /// Returns true for `Not_found`
+    pub fn is_not_found(&self) -> bool {
+        self.is_predefined() && self.is("Not_found")
+    }
+
+    /// Returns the message of a `Failure` exception
+    pub fn failure(&self) -> Option<&str> {
+        self.predefined_message("Failure")
+    }
+
+    /// Returns the message of an `Invalid_argument` exception
+    pub fn invalid_argument(&self) -> Option<&str> {
+        self.predefined_message("Invalid_argument")
+    }
+
+    /// Returns the message of a `Sys_error` exception
+    pub fn sys_error(&self) -> Option<&str> {
+        self.predefined_message("Sys_error")
+    }
+
+    fn predefined_message(&self, name: &str) -> Option<&str> {
+        if !self.is_predefined() || !self.is(name) {
+            return None;
+        }
+        self.arg(0)
+    }
+}
+
+/// Formats the exception using `caml_format_exception`, this produces the same text as
+/// `Printexc.to_string` when no printers have been registered, except for the exceptions that
+/// `Printexc` formats as a sentence, like `Out_of_memory` or `Assert_failure`
+impl core::fmt::Display for Exn {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        unsafe {
+            let ptr = crate::sys::caml_format_exception((self.0).0);
+            if ptr.is_null() {
+                return f.write_str(self.name());
+            }
+            let res = match core::ffi::CStr::from_ptr(ptr).to_str() {
+                Ok(s) => f.write_str(s),
+                Err(_) => f.write_str(self.name()),
+            };
+            crate::sys::caml_stat_free(ptr as *mut core::ffi::c_void);
+            res
+        }
+    }
+}
+
 /// Error returned by `ocaml-rs` functions
 #[derive(Debug)]
 pub enum Error {
@@ -50,20 +378,222 @@ pub enum Error {
     NotDoubleArray,
 
     /// Error message
-    Message(&'static str),
+    Message(Cow<'static, str>),
 
     /// General error
     #[cfg(not(feature = "no-std"))]
-    Error(Box<dyn std::error::Error>),
+    Error(RustError),
 
     /// OCaml exceptions
     Caml(CamlError),
+
+    /// An error returned by `Value::call` with the OCaml backtrace of the exception, see
+    /// `runtime::capture_backtraces`
+    #[cfg(not(feature = "no-std"))]
+    WithBacktrace(Box<Error>, CamlBacktrace),
+}
+
+/// OCaml backtrace of an exception returned to Rust, along with the Rust backtrace at the point
+/// the exception was returned. This is only captured when enabled using
+/// `runtime::capture_backtraces`
+#[cfg(not(feature = "no-std"))]
+pub struct CamlBacktrace {
+    frames: Vec<String>,
+    rust: std::backtrace::Backtrace,
+}
+
+#[cfg(not(feature = "no-std"))]
+impl CamlBacktrace {
+    /// Capture the backtrace of the most recent exception, `None` is returned when OCaml isn't
+    /// recording backtraces
+    pub(crate) unsafe fn capture() -> Option<CamlBacktrace> {
+        use crate::sys;
+
+        if Value::new(sys::caml_backtrace_status(Value::unit().0)) != Value::bool(true) {
+            return None;
+        }
+
+        let slots: Option<Value> = FromValue::from_value(Value::new(
+            sys::caml_get_exception_backtrace(Value::unit().0),
+        ));
+        let slots = slots?;
+        let mut frames = Vec::new();
+        for i in 0..slots.array_length() {
+            let slot: Value = slots.field(i);
+            let is_raise: bool = slot.field(0);
+            let info = match (is_raise, i) {
+                (true, 0) => "Raised at",
+                (true, _) => "Re-raised at",
+                (false, 0) => "Raised by primitive operation at",
+                (false, _) => "Called from",
+            };
+
+            // Known_location has tag 0, the first fields are the same in every OCaml version
+            if slot.tag() == Tag(0) {
+                let file: &str = slot.field(1);
+                let line: crate::Int = slot.field(2);
+                let start: crate::Int = slot.field(3);
+                let end: crate::Int = slot.field(4);
+                frames.push(format!(
+                    "{} file \"{}\", line {}, characters {}-{}",
+                    info, file, line, start, end
+                ));
+            } else if !is_raise {
+                frames.push(format!("{} unknown location", info));
+            }
+        }
+
+        Some(CamlBacktrace {
+            frames,
+            rust: std::backtrace::Backtrace::capture(),
+        })
+    }
+
+    /// OCaml backtrace, formatted like `Printexc.print_backtrace`
+    pub fn frames(&self) -> &[String] {
+        &self.frames
+    }
+
+    /// Rust backtrace, this is only available when enabled using `RUST_BACKTRACE` or
+    /// `RUST_LIB_BACKTRACE`
+    pub fn rust(&self) -> Option<&std::backtrace::Backtrace> {
+        match self.rust.status() {
+            std::backtrace::BacktraceStatus::Captured => Some(&self.rust),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(not(feature = "no-std"))]
+impl core::fmt::Display for CamlBacktrace {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        for frame in &self.frames {
+            writeln!(f, "{}", frame)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "no-std"))]
+impl core::fmt::Debug for CamlBacktrace {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "\nOCaml backtrace:\n{}", self)?;
+        if let Some(rust) = self.rust() {
+            write!(f, "Rust backtrace:\n{}", rust)?;
+        }
+        Ok(())
+    }
 }
 
 #[cfg(not(feature = "no-std"))]
 impl<T: 'static + std::error::Error> From<T> for Error {
     fn from(x: T) -> Error {
-        Error::Error(Box::new(x))
+        Error::Error(RustError {
+            type_name: core::any::type_name::<T>(),
+            error: Box::new(x),
+            backtrace: std::backtrace::Backtrace::capture(),
+        })
+    }
+}
+
+/// A Rust error wrapped by `Error::Error`, the type name and a backtrace are recorded when the
+/// error is converted so they can be passed to OCaml.
+///
+/// When an exception has been registered as `Rust_error_v2` using `Callback.register_exception`
+/// these errors are raised as that exception with a record containing the type name, message, the
+/// messages of each `source()` and the backtrace. Otherwise they're raised as `Failure` with the
+/// `Debug` output of the wrapped error as the message, which is what all versions before 0.21 did.
+/// Earlier examples registered `Rust_error` with a `string` argument, so the record exception uses
+/// a different name:
+///
+/// ```ocaml
+/// type t = {
+///   type_name : string;
+///   message : string;
+///   causes : string list;
+///   backtrace : string option;
+/// }
+///
+/// exception Rust_error of t
+///
+/// let () =
+///   Callback.register_exception "Rust_error_v2"
+///     (Rust_error { type_name = ""; message = ""; causes = []; backtrace = None })
+/// ```
+///
+/// The `ocaml-rs.rust_error` library in the `ocaml` directory of the `ocaml-rs` repository defines
+/// and registers the exception and provides some helper functions.
+#[cfg(not(feature = "no-std"))]
+pub struct RustError {
+    type_name: &'static str,
+    error: Box<dyn std::error::Error>,
+    backtrace: std::backtrace::Backtrace,
+}
+
+#[cfg(not(feature = "no-std"))]
+impl RustError {
+    /// Name of the original error type
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// Backtrace captured when the error was converted, this is only available when enabled
+    /// using `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE`
+    pub fn backtrace(&self) -> Option<&std::backtrace::Backtrace> {
+        match self.backtrace.status() {
+            std::backtrace::BacktraceStatus::Captured => Some(&self.backtrace),
+            _ => None,
+        }
+    }
+
+    /// Messages of the errors in the `source()` chain, the outermost cause comes first
+    pub fn causes(&self) -> impl Iterator<Item = String> + '_ {
+        let mut source = self.error.source();
+        core::iter::from_fn(move || {
+            let s = source?;
+            source = s.source();
+            Some(s.to_string())
+        })
+    }
+
+    /// Returns the original error
+    pub fn into_inner(self) -> Box<dyn std::error::Error> {
+        self.error
+    }
+
+    /// Message of the `Failure` raised when `Rust_error_v2` hasn't been registered
+    pub(crate) fn failure_message(&self) -> String {
+        format!("{:?}", self.error)
+    }
+
+    /// Convert to the payload of the OCaml `Rust_error_v2` exception
+    fn payload(&self, rt: &Runtime) -> Value {
+        let causes: std::collections::LinkedList<String> = self.causes().collect();
+        let backtrace = self.backtrace().map(|bt| bt.to_string());
+        (self.type_name, self.error.to_string(), causes, backtrace).into_value(rt)
+    }
+}
+
+#[cfg(not(feature = "no-std"))]
+impl core::ops::Deref for RustError {
+    type Target = dyn std::error::Error;
+
+    fn deref(&self) -> &Self::Target {
+        &*self.error
+    }
+}
+
+#[cfg(not(feature = "no-std"))]
+impl core::fmt::Debug for RustError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        core::fmt::Debug::fmt(&self.error, f)
+    }
+}
+
+#[cfg(not(feature = "no-std"))]
+impl core::fmt::Display for RustError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        core::fmt::Display::fmt(&self.error, f)
     }
 }
 
@@ -86,7 +616,7 @@ impl Error {
             Some(v) => v,
             None => {
                 return Err(Error::Message(
-                    "Value has not been registered with the OCaml runtime",
+                    "Value has not been registered with the OCaml runtime".into(),
                 ))
             }
         };
@@ -104,7 +634,7 @@ impl Error {
             Some(v) => v,
             None => {
                 return Err(Error::Message(
-                    "Value has not been registered with the OCaml runtime",
+                    "Value has not been registered with the OCaml runtime".into(),
                 ))
             }
         };
@@ -123,13 +653,27 @@ impl Error {
     }
 
     /// Raise `Failure`
-    pub fn failwith(s: &'static str) -> Result<(), Error> {
-        Err(CamlError::Failure(s).into())
+    pub fn failwith<S: Into<Cow<'static, str>>>(s: S) -> Result<(), Error> {
+        Err(CamlError::Failure(s.into()).into())
     }
 
     /// Raise `Invalid_argument`
-    pub fn invalid_argument(s: &'static str) -> Result<(), Error> {
-        Err(CamlError::Failure(s).into())
+    pub fn invalid_argument<S: Into<Cow<'static, str>>>(s: S) -> Result<(), Error> {
+        Err(CamlError::InvalidArgument(s.into()).into())
+    }
+
+    /// Raise `Sys_error`
+    pub fn sys_error<S: Into<Cow<'static, str>>>(s: S) -> Result<(), Error> {
+        Err(CamlError::SysError(s.into()).into())
+    }
+
+    #[doc(hidden)]
+    pub fn format_failure(args: core::fmt::Arguments) -> Error {
+        let s = match args.as_str() {
+            Some(s) => Cow::Borrowed(s),
+            None => Cow::Owned(crate::util::format(args)),
+        };
+        CamlError::Failure(s).into()
     }
 
     #[doc(hidden)]
@@ -152,29 +696,146 @@ impl Error {
         loop {}
     }
 
+    /// OCaml backtrace of the exception that caused this error, see
+    /// `runtime::capture_backtraces`
+    #[cfg(not(feature = "no-std"))]
+    pub fn caml_backtrace(&self) -> Option<&CamlBacktrace> {
+        match self {
+            Error::WithBacktrace(_, bt) => Some(bt),
+            _ => None,
+        }
+    }
+
+    #[doc(hidden)]
+    /// Convert an exception returned by a callback, capturing the backtrace if enabled
+    pub unsafe fn from_callback_exception(rt: &Runtime, exn: Value) -> Error {
+        #[cfg(not(feature = "no-std"))]
+        if crate::runtime::capture_backtraces_enabled() {
+            // Capturing the backtrace allocates, so the exception has to be rooted
+            let root = crate::Root::new(rt, exn);
+            let bt = CamlBacktrace::capture();
+            let err = CamlError::from_exception(root.value(rt)).into();
+            return match bt {
+                Some(bt) => Error::WithBacktrace(Box::new(err), bt),
+                None => err,
+            };
+        }
+
+        #[cfg(feature = "no-std")]
+        let _ = rt;
+
+        CamlError::from_exception(exn).into()
+    }
+
     /// Get named error registered using `Callback.register_exception`
     pub fn named<S: AsRef<str>>(s: S) -> Option<Value> {
         unsafe { Value::named(s.as_ref()) }
     }
 }
 
+/// Rust types that map to OCaml exceptions registered using `Callback.register_exception`, this
+/// is typically implemented using `#[derive(ocaml::Exception)]`
+pub unsafe trait Exception: Sized {
+    /// Convert to an OCaml exception value, an error is returned when the exception hasn't been
+    /// registered
+    fn into_exception(self, rt: &Runtime) -> Result<Value, Error>;
+
+    /// Convert from an OCaml exception value, returns `None` if the exception doesn't belong to
+    /// this type
+    fn from_exception(exn: Value) -> Option<Self>;
+
+    /// Convert the exception contained in an `Error`, such as the errors returned by
+    /// `Value::call`
+    fn from_error(err: &Error) -> Option<Self> {
+        match err {
+            Error::Caml(CamlError::Exception(exn)) => Self::from_exception(*exn),
+            #[cfg(not(feature = "no-std"))]
+            Error::WithBacktrace(err, _) => Self::from_error(err),
+            _ => None,
+        }
+    }
+}
+
+/// `Raise` wraps an `Exception` so it can be converted to `Error`, this allows an `Exception` to be
+/// returned in a `Result` from `#[ocaml::func]` functions, or used with `?`, even when it also
+/// implements `std::error::Error`
+///
+/// ```rust,no_run
+/// #[derive(ocaml::Exception)]
+/// pub enum MyError {
+///     Empty,
+/// }
+///
+/// #[ocaml::func]
+/// pub fn non_empty(s: &str) -> Result<ocaml::Int, ocaml::Raise<MyError>> {
+///     if s.is_empty() {
+///         return Err(MyError::Empty.into());
+///     }
+///     Ok(s.len() as ocaml::Int)
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Raise<E>(pub E);
+
+impl<E: Exception> From<E> for Raise<E> {
+    fn from(e: E) -> Raise<E> {
+        Raise(e)
+    }
+}
+
+impl<E: Exception> From<Raise<E>> for Error {
+    fn from(e: Raise<E>) -> Error {
+        let rt = unsafe { Runtime::recover_handle() };
+        match e.0.into_exception(rt) {
+            Ok(exn) => Error::Caml(CamlError::Exception(exn)),
+            Err(e) => e,
+        }
+    }
+}
+
+/// Copy an error message to the OCaml heap, the Rust string is dropped before returning since
+/// raising an exception never returns to the caller
+fn message(rt: &Runtime, s: Cow<'static, str>) -> Value {
+    s.as_ref().into_value(rt)
+}
+
+/// Allocate the `string * int * int` argument of `Match_failure`, `Assert_failure` and
+/// `Undefined_recursive_module`
+fn location(rt: &Runtime, file: Cow<'static, str>, line: crate::Int, col: crate::Int) -> Value {
+    (file.as_ref(), line, col).into_value(rt)
+}
+
+/// Name of the exception used to raise `Error::Error`
 #[cfg(not(feature = "no-std"))]
-unsafe impl<T: IntoValue, E: 'static + std::error::Error> IntoValue for Result<T, E> {
-    fn into_value(self, rt: &Runtime) -> Value {
-        match self {
-            Ok(x) => x.into_value(rt),
-            Err(y) => {
-                let e: Result<T, Error> = Err(Error::Error(Box::new(y)));
-                e.into_value(rt)
-            }
+pub(crate) const RUST_ERROR: &str = "Rust_error_v2";
+
+/// Raise a predefined exception that can't be raised using the C API, the exception constructor
+/// comes from the OCaml runtime except for `Exit`, which has to be registered. `Failure` is raised
+/// when the constructor can't be found
+fn raise_predefined(rt: &Runtime, err: CamlError, name: &str) {
+    let exn = err.to_exception(rt);
+    drop(err);
+    match exn {
+        Some(exn) => unsafe { crate::sys::caml_raise(exn.0) },
+        None => {
+            let s = crate::util::format(format_args!(
+                "{} could not be found in the OCaml runtime",
+                name
+            ));
+            let s = message(rt, s.into());
+            unsafe { crate::sys::caml_failwith_value(s.0) }
         }
     }
 }
 
-unsafe impl<T: IntoValue> IntoValue for Result<T, Error> {
+unsafe impl<T: IntoValue, E: Into<Error>> IntoValue for Result<T, E> {
     fn into_value(self, rt: &Runtime) -> Value {
-        match self {
+        match self.map_err(Into::into) {
             Ok(x) => return x.into_value(rt),
+            #[cfg(not(feature = "no-std"))]
+            Err(Error::WithBacktrace(err, _)) => {
+                return Err::<Value, Error>(*err).into_value(rt);
+            }
             Err(Error::Caml(CamlError::Exception(e))) => unsafe {
                 crate::sys::caml_raise(e.0);
             },
@@ -185,7 +846,7 @@ unsafe impl<T: IntoValue> IntoValue for Result<T, Error> {
                 crate::sys::caml_array_bound_error();
             },
             Err(Error::Caml(CamlError::OutOfMemory)) => unsafe {
-                crate::sys::caml_array_bound_error();
+                crate::sys::caml_raise_out_of_memory();
             },
             Err(Error::Caml(CamlError::EndOfFile)) => unsafe {
                 crate::sys::caml_raise_end_of_file()
@@ -199,46 +860,54 @@ unsafe impl<T: IntoValue> IntoValue for Result<T, Error> {
             Err(Error::Caml(CamlError::SysBlockedIo)) => unsafe {
                 crate::sys::caml_raise_sys_blocked_io()
             },
-            Err(Error::Caml(CamlError::InvalidArgument(s))) => {
-                unsafe {
-                    let s = crate::util::CString::new(s).expect("Invalid C string");
-                    crate::sys::caml_invalid_argument(s.as_ptr() as *const ocaml_sys::Char)
-                };
-            }
+            Err(Error::Caml(CamlError::InvalidArgument(s))) => unsafe {
+                let s = message(rt, s);
+                crate::sys::caml_invalid_argument_value(s.0)
+            },
             Err(Error::Caml(CamlError::WithArg(a, b))) => unsafe {
                 crate::sys::caml_raise_with_arg(a.0, b.0)
             },
-            Err(Error::Caml(CamlError::SysError(s))) => {
-                unsafe {
-                    let s = s.into_value(rt);
-                    crate::sys::caml_raise_sys_error(s.0)
-                };
+            Err(Error::Caml(err @ CamlError::Exit)) => raise_predefined(rt, err, "Exit"),
+            Err(Error::Caml(err @ CamlError::MatchFailure(..))) => {
+                raise_predefined(rt, err, "Match_failure")
             }
-            Err(Error::Message(s)) => {
-                unsafe {
-                    let s = crate::util::CString::new(s).expect("Invalid C string");
-                    crate::sys::caml_failwith(s.as_ptr() as *const ocaml_sys::Char)
-                };
+            Err(Error::Caml(err @ CamlError::AssertFailure(..))) => {
+                raise_predefined(rt, err, "Assert_failure")
             }
-            Err(Error::Caml(CamlError::Failure(s))) => {
-                unsafe {
-                    let s = crate::util::CString::new(s).expect("Invalid C string");
-                    crate::sys::caml_failwith(s.as_ptr() as *const ocaml_sys::Char)
-                };
+            Err(Error::Caml(err @ CamlError::UndefinedRecursiveModule(..))) => {
+                raise_predefined(rt, err, "Undefined_recursive_module")
             }
+            Err(Error::Caml(CamlError::SysError(s))) => unsafe {
+                let s = message(rt, s);
+                crate::sys::caml_raise_sys_error(s.0)
+            },
+            Err(Error::Message(s)) | Err(Error::Caml(CamlError::Failure(s))) => unsafe {
+                let s = message(rt, s);
+                crate::sys::caml_failwith_value(s.0)
+            },
             #[cfg(not(feature = "no-std"))]
-            Err(Error::Error(e)) => {
-                let s = format!("{:?}\0", e);
-                unsafe { crate::sys::caml_failwith(s.as_ptr() as *const ocaml_sys::Char) };
-            }
-            Err(Error::NotDoubleArray) => {
-                let s = "invalid double array\0";
-                unsafe { crate::sys::caml_failwith(s.as_ptr() as *const ocaml_sys::Char) };
-            }
-            Err(Error::NotCallable) => {
-                let s = "value is not callable\0";
-                unsafe { crate::sys::caml_failwith(s.as_ptr() as *const ocaml_sys::Char) };
-            }
+            Err(Error::Error(e)) => unsafe {
+                if Error::named(RUST_ERROR).is_some() {
+                    let payload = e.payload(rt);
+                    drop(e);
+                    // The exception is looked up again since allocating may have moved it
+                    let exn = Error::named(RUST_ERROR).unwrap();
+                    crate::sys::caml_raise_with_arg(exn.0, payload.0)
+                } else {
+                    let s = e.failure_message();
+                    drop(e);
+                    let s = message(rt, s.into());
+                    crate::sys::caml_failwith_value(s.0)
+                }
+            },
+            Err(Error::NotDoubleArray) => unsafe {
+                let s = message(rt, "invalid double array".into());
+                crate::sys::caml_failwith_value(s.0)
+            },
+            Err(Error::NotCallable) => unsafe {
+                let s = message(rt, "value is not callable".into());
+                crate::sys::caml_failwith_value(s.0)
+            },
         };
 
         Value::unit()
@@ -249,7 +918,7 @@ unsafe impl<T: FromValue> FromValue for Result<T, crate::Error> {
     fn from_value(value: Value) -> Result<T, crate::Error> {
         unsafe {
             if value.is_exception_result() {
-                return Err(CamlError::Exception(value.exception().unwrap()).into());
+                return Err(CamlError::from_exception(value.exception().unwrap()).into());
             }
 
             Ok(T::from_value(value))
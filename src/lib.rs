@@ -112,6 +112,9 @@
 #[cfg(all(feature = "link", feature = "no-std"))]
 std::compile_error!("Cannot use link and no-std features");
 
+#[cfg(feature = "no-std")]
+extern crate alloc;
+
 pub use ocaml_interop::{self as interop, OCaml, OCamlRef, OCamlRuntime as Runtime};
 
 /// The `sys` module contains the low-level implementation of the OCaml runtime
@@ -120,14 +123,21 @@ pub use ocaml_sys as sys;
 #[cfg(feature = "derive")]
 pub use ocaml_derive::{
     ocaml_bytecode_func as bytecode_func, ocaml_func as func, ocaml_native_func as native_func,
-    FromValue, IntoValue,
+    ocaml_object as object, ocaml_sig as sig, Exception, FromValue, IntoValue, TryFromValue,
 };
 
 #[macro_use]
 mod macros;
 
+#[cfg(not(feature = "no-std"))]
+mod closure;
 mod conv;
+#[cfg(not(feature = "no-std"))]
+mod decode;
 mod error;
+mod named;
+#[cfg(not(feature = "no-std"))]
+mod root;
 mod tag;
 mod types;
 mod util;
@@ -140,10 +150,17 @@ pub mod runtime;
 pub mod custom;
 
 pub use crate::custom::Custom;
-pub use crate::error::{CamlError, Error};
+#[cfg(not(feature = "no-std"))]
+pub use crate::decode::{DecodeError, PathSegment, TryFromValue};
+#[cfg(not(feature = "no-std"))]
+pub use crate::error::{CamlBacktrace, RustError};
+pub use crate::error::{CamlError, Error, Exception, Exn, Raise};
+pub use crate::named::NamedValue;
+#[cfg(not(feature = "no-std"))]
+pub use crate::root::Root;
 pub use crate::runtime::*;
 pub use crate::tag::Tag;
-pub use crate::types::{bigarray, Array, List, Pointer};
+pub use crate::types::{bigarray, Array, FloatArray, Fn1, Fn2, Fn3, Fn4, Fn5, List, Pointer};
 pub use crate::value::{FromValue, IntoValue, Value};
 
 #[cfg(not(feature = "no-std"))]
@@ -130,6 +130,26 @@ macro_rules! body {
     }};
 }
 
+/// `failwith!` returns early with an error that raises `Failure` when it's converted to an OCaml
+/// value, the message is formatted using `format!` syntax
+///
+/// ```rust
+/// fn open(path: &str) -> Result<(), ocaml::Error> {
+///     if path.is_empty() {
+///         ocaml::failwith!("invalid path: {:?}", path);
+///     }
+///     Ok(())
+/// }
+/// ```
+#[macro_export]
+macro_rules! failwith {
+    ($($arg:tt)+) => {
+        return ::core::result::Result::Err(
+            $crate::Error::format_failure(::core::format_args!($($arg)+)).into(),
+        )
+    };
+}
+
 #[macro_export]
 /// Convenience macro to create an OCaml array
 macro_rules! array {
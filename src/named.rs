@@ -0,0 +1,108 @@
+use crate::{sys, Error, FromValue, IntoValue, Runtime, Value};
+
+use core::{
+    marker::PhantomData,
+    sync::atomic::{AtomicPtr, Ordering},
+};
+
+/// `NamedValue<T>` looks up a value registered by OCaml using `Callback.register`. The pointer
+/// returned by the runtime is cached after the first successful lookup, so it can be stored in a
+/// `static` and used repeatedly without searching the named value table each time.
+///
+/// `ocaml::named!` creates a static `NamedValue` and returns a reference to it:
+///
+/// ```rust,no_run
+/// # fn f(gc: &ocaml::Runtime) -> Result<(), ocaml::Error> {
+/// // let () = Callback.register "my_callback" (fun x -> x + 1)
+/// let x = ocaml::named!("my_callback": ocaml::Fn1<ocaml::Int, ocaml::Int>).call(gc, 1)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct NamedValue<T> {
+    name: &'static str,
+    missing: &'static str,
+    ptr: AtomicPtr<sys::Value>,
+    _t: PhantomData<fn() -> T>,
+}
+
+impl<T> NamedValue<T> {
+    /// Create a lookup for the value registered as `name`, `missing` is the error message returned
+    /// when nothing has been registered under that name
+    pub const fn new(name: &'static str, missing: &'static str) -> NamedValue<T> {
+        NamedValue {
+            name,
+            missing,
+            ptr: AtomicPtr::new(core::ptr::null_mut()),
+            _t: PhantomData,
+        }
+    }
+
+    /// Name of the value
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Get the current value without converting it, the value may have been updated by calling
+    /// `Callback.register` again
+    pub fn value(&self, _rt: &Runtime) -> Result<Value, Error> {
+        let mut ptr = self.ptr.load(Ordering::Relaxed);
+        if ptr.is_null() {
+            let name = crate::util::CString::new(self.name)
+                .map_err(|_| Error::Message(self.missing.into()))?;
+            ptr = unsafe { sys::caml_named_value(name.as_ptr()) } as *mut sys::Value;
+            if ptr.is_null() {
+                return Err(Error::Message(self.missing.into()));
+            }
+            self.ptr.store(ptr, Ordering::Relaxed);
+        }
+        Ok(unsafe { Value::new(*ptr) })
+    }
+
+    /// Get the current value converted to `T`
+    pub fn get(&self, rt: &Runtime) -> Result<T, Error>
+    where
+        T: FromValue,
+    {
+        self.value(rt).map(T::from_value)
+    }
+}
+
+macro_rules! named_call {
+    ($name:ident($($arg:ident: $t:ident),*)) => {
+        impl<$($t: IntoValue,)* R: FromValue> NamedValue<crate::$name<$($t,)* R>> {
+            /// Call the named function, see `get` and the `call` method of the function type
+            pub fn call(&self, rt: &Runtime, $($arg: $t),*) -> Result<R, Error> {
+                self.get(rt)?.call(rt, $($arg),*)
+            }
+        }
+    };
+}
+
+named_call!(Fn1(a: A));
+named_call!(Fn2(a: A, b: B));
+named_call!(Fn3(a: A, b: B, c: C));
+named_call!(Fn4(a: A, b: B, c: C, d: D));
+named_call!(Fn5(a: A, b: B, c: C, d: D, e: E));
+
+/// Returns a `&'static NamedValue<T>` for the value registered by OCaml under the given name
+///
+/// ```rust,no_run
+/// # fn f(gc: &ocaml::Runtime) -> Result<(), ocaml::Error> {
+/// let f: ocaml::Value = ocaml::named!("my_value": ocaml::Value).get(gc)?;
+/// # Ok(())
+/// # }
+/// ```
+#[macro_export]
+macro_rules! named {
+    ($name:literal : $t:ty) => {{
+        static NAMED: $crate::NamedValue<$t> = $crate::NamedValue::new(
+            $name,
+            concat!(
+                "named value ",
+                $name,
+                " has not been registered using Callback.register"
+            ),
+        );
+        &NAMED
+    }};
+}
@@ -0,0 +1,139 @@
+use crate::{sys, FromValue, IntoValue, Runtime, Value};
+
+use core::marker::PhantomData;
+
+/// `Root<T>` keeps an OCaml value alive while it's owned by Rust, for example to store OCaml
+/// callbacks in Rust structs. The value is registered as a generational global root when the
+/// `Root` is created and removed when it's dropped, the garbage collector updates the root when the
+/// value is moved.
+///
+/// A `Root` can be sent to other threads, but it can only be accessed using a `Runtime` handle.
+/// Removing the root from the runtime requires the runtime lock, so a `Root` dropped on a thread
+/// other than the one that created it is queued and removed the next time a `Root` is used or the
+/// lock is acquired using `runtime::acquire_lock`. Use `Root::release` to remove it immediately
+/// from a thread holding the lock.
+///
+/// ```rust,no_run
+/// struct Handler {
+///     callback: ocaml::Root<ocaml::Fn1<ocaml::Int, ocaml::Int>>,
+/// }
+///
+/// impl Handler {
+///     fn handle(&self, rt: &ocaml::Runtime, x: ocaml::Int) -> Result<ocaml::Int, ocaml::Error> {
+///         self.callback.get(rt).call(rt, x)
+///     }
+/// }
+/// ```
+pub struct Root<T> {
+    // Boxed so the address registered with the runtime doesn't change when the `Root` is moved
+    value: Box<sys::Value>,
+    #[cfg(not(feature = "no-std"))]
+    thread: std::thread::ThreadId,
+    _t: PhantomData<T>,
+}
+
+#[cfg(not(feature = "no-std"))]
+unsafe impl<T: Send> Send for Root<T> {}
+
+/// Roots dropped on a thread other than the one that created them, waiting to be removed by a
+/// thread holding the runtime lock. The boxes are kept since their addresses are the registered
+/// roots
+#[cfg(not(feature = "no-std"))]
+#[allow(clippy::vec_box)]
+pub(crate) static PENDING: std::sync::Mutex<Vec<Box<sys::Value>>> =
+    std::sync::Mutex::new(Vec::new());
+
+/// Set when `PENDING` isn't empty, so using a `Root` doesn't have to lock the queue
+#[cfg(not(feature = "no-std"))]
+static HAS_PENDING: core::sync::atomic::AtomicBool = core::sync::atomic::AtomicBool::new(false);
+
+/// Remove the roots queued by `Drop`
+#[cfg(not(feature = "no-std"))]
+pub(crate) fn release_pending(_rt: &Runtime) {
+    if !HAS_PENDING.swap(false, core::sync::atomic::Ordering::Acquire) {
+        return;
+    }
+
+    let pending = core::mem::take(&mut *PENDING.lock().unwrap_or_else(|e| e.into_inner()));
+    for mut value in pending {
+        unsafe { sys::caml_remove_generational_global_root(&mut *value) }
+    }
+}
+
+impl<T: IntoValue> Root<T> {
+    /// Convert `x` to an OCaml value and register it as a global root
+    pub fn new(rt: &Runtime, x: T) -> Root<T> {
+        #[cfg(not(feature = "no-std"))]
+        release_pending(rt);
+
+        let mut value = Box::new(x.into_value(rt).0);
+        unsafe { sys::caml_register_generational_global_root(&mut *value) };
+        Root {
+            value,
+            #[cfg(not(feature = "no-std"))]
+            thread: std::thread::current().id(),
+            _t: PhantomData,
+        }
+    }
+
+    /// Replace the rooted value
+    pub fn set(&mut self, rt: &Runtime, x: T) {
+        #[cfg(not(feature = "no-std"))]
+        release_pending(rt);
+
+        let x = x.into_value(rt);
+        unsafe { sys::caml_modify_generational_global_root(&mut *self.value, x.0) }
+    }
+}
+
+impl<T> Root<T> {
+    /// Remove the root, this can be called from any thread holding the runtime lock
+    pub fn release(self, rt: &Runtime) {
+        #[cfg(not(feature = "no-std"))]
+        release_pending(rt);
+
+        // Take the value out so `Drop` doesn't queue it again
+        let mut value = unsafe { core::ptr::read(&self.value) };
+        core::mem::forget(self);
+        unsafe { sys::caml_remove_generational_global_root(&mut *value) }
+    }
+
+    /// Get the rooted value without converting it
+    pub fn value(&self, rt: &Runtime) -> Value {
+        #[cfg(not(feature = "no-std"))]
+        release_pending(rt);
+
+        let _ = rt;
+        unsafe { Value::new(*self.value) }
+    }
+}
+
+impl<T: FromValue> Root<T> {
+    /// Convert the rooted value to `T`
+    pub fn get(&self, rt: &Runtime) -> T {
+        T::from_value(self.value(rt))
+    }
+}
+
+impl<T> Drop for Root<T> {
+    fn drop(&mut self) {
+        #[cfg(not(feature = "no-std"))]
+        if std::thread::current().id() != self.thread {
+            let value = core::mem::replace(&mut self.value, Box::new(sys::UNIT));
+            PENDING
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(value);
+            HAS_PENDING.store(true, core::sync::atomic::Ordering::Release);
+            return;
+        }
+
+        unsafe { sys::caml_remove_generational_global_root(&mut *self.value) }
+    }
+}
+
+unsafe impl<T> IntoValue for &Root<T> {
+    fn into_value(self, rt: &Runtime) -> Value {
+        self.value(rt)
+    }
+}
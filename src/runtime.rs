@@ -3,14 +3,21 @@ use crate::sys;
 #[cfg(not(feature = "no-std"))]
 static RUNTIME: std::sync::Once = std::sync::Once::new();
 
+#[cfg(not(feature = "no-std"))]
+static CAPTURE_BACKTRACES: core::sync::atomic::AtomicBool =
+    core::sync::atomic::AtomicBool::new(false);
+
 /// Release global lock
 pub fn release_lock() {
     unsafe { sys::caml_enter_blocking_section() }
 }
 
-/// Obtain global lock
+/// Obtain global lock, this also removes the `Root`s that were dropped on other threads
 pub fn acquire_lock() {
     unsafe { sys::caml_leave_blocking_section() }
+
+    #[cfg(not(feature = "no-std"))]
+    crate::root::release_pending(unsafe { crate::Runtime::recover_handle() });
 }
 
 /// Execute a function with the OCaml global lock
@@ -50,6 +57,21 @@ pub fn init() {
     })
 }
 
+/// Capture the OCaml backtrace when `Value::call` or `Value::call_n` returns an exception, the
+/// backtrace is stored in `Error::WithBacktrace`. OCaml only records backtraces after
+/// `Printexc.record_backtrace true` has been called or when `OCAMLRUNPARAM` contains `b`, nothing
+/// is captured otherwise
+#[cfg(not(feature = "no-std"))]
+pub fn capture_backtraces(enable: bool) {
+    CAPTURE_BACKTRACES.store(enable, core::sync::atomic::Ordering::Relaxed)
+}
+
+/// Returns true when backtraces are captured, see `capture_backtraces`
+#[cfg(not(feature = "no-std"))]
+pub fn capture_backtraces_enabled() -> bool {
+    CAPTURE_BACKTRACES.load(core::sync::atomic::Ordering::Relaxed)
+}
+
 /// Shutdown and cleanup OCaml runtime
 pub fn shutdown() {
     unsafe { crate::sys::caml_shutdown() }
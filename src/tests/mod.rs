@@ -88,3 +88,58 @@ fn test_int() {
         assert_eq!(e_, 0);
     })
 }
+
+#[test]
+fn test_root() {
+    ocaml::runtime::init();
+    ocaml::body!(gc: {
+        let mut root = ocaml::Root::new(gc, "testing".to_string());
+        assert_eq!(root.get(gc), "testing");
+
+        root.set(gc, "123".to_string());
+        let s: String = FromValue::from_value((&root).into_value(gc));
+        assert_eq!(s, "123");
+    })
+}
+
+#[test]
+fn test_rust_error_unregistered() {
+    ocaml::runtime::init();
+
+    // `Rust_error_v2` isn't registered in this program, so `Error::Error` is raised as `Failure`
+    // with the `Debug` output of the wrapped error
+    assert!(Error::named(crate::error::RUST_ERROR).is_none());
+    let err: Error = "abc".parse::<i32>().unwrap_err().into();
+    match err {
+        Error::Error(e) => {
+            assert_eq!(e.type_name(), "core::num::error::ParseIntError");
+            assert_eq!(e.failure_message(), "ParseIntError { kind: InvalidDigit }");
+        }
+        _ => panic!("expected Error::Error"),
+    }
+}
+
+#[test]
+fn test_root_other_thread() {
+    ocaml::runtime::init();
+    ocaml::body!(gc: {
+        // Dropped on another thread, the root is queued until the next `Root::new`
+        let root = ocaml::Root::new(gc, "testing".to_string());
+        std::thread::spawn(move || drop(root)).join().unwrap();
+        assert_eq!(crate::root::PENDING.lock().unwrap().len(), 1);
+        let root = ocaml::Root::new(gc, "123".to_string());
+        assert!(crate::root::PENDING.lock().unwrap().is_empty());
+
+        // Or until another root is used
+        let other = ocaml::Root::new(gc, "456".to_string());
+        std::thread::spawn(move || drop(other)).join().unwrap();
+        assert_eq!(root.get(gc), "123");
+        assert!(crate::root::PENDING.lock().unwrap().is_empty());
+
+        // Sent to another thread and back, then released on the thread holding the lock
+        let root = std::thread::spawn(move || root).join().unwrap();
+        assert_eq!(root.get(gc), "123");
+        root.release(gc);
+        assert!(crate::root::PENDING.lock().unwrap().is_empty());
+    })
+}
@@ -1,5 +1,6 @@
 //! OCaml types represented in Rust, these are zero-copy and incur no additional overhead
 
+use crate::util::Sealed;
 use crate::{sys, CamlError, Error, Runtime};
 
 use core::{
@@ -177,14 +178,20 @@ impl<T: IntoValue + FromValue> Array<T> {
     /// Allocate a new Array
     pub fn alloc(rt: &Runtime, n: usize) -> Array<T> {
         let x = crate::frame!(rt: (x) {
-            x = unsafe { Value::new(sys::caml_alloc(n, 0)) };
+            x = unsafe {
+                if <T as FromValue>::from_float(Sealed(())).is_some() {
+                    Value::new(sys::caml_alloc_float_array(n))
+                } else {
+                    Value::new(sys::caml_alloc(n, 0))
+                }
+            };
             x
         });
         Array(x, PhantomData)
     }
 
-    /// Check if Array contains only doubles, if so `get_double` and `set_double` should be used
-    /// to access values
+    /// Check if Array contains only unboxed doubles, `get` and `set` handle this automatically for
+    /// `f32` and `f64` elements
     pub fn is_double_array(&self) -> bool {
         unsafe { sys::caml_is_double_array((self.0).0) == 1 }
     }
@@ -215,6 +222,12 @@ impl<T: IntoValue + FromValue> Array<T> {
     /// This function does not perform bounds checking
     #[inline]
     pub unsafe fn set_unchecked(&mut self, rt: &Runtime, i: usize, v: T) {
+        if let Some(into_float) = <T as IntoValue>::into_float(Sealed(())) {
+            if self.is_double_array() {
+                *((self.0).0 as *mut f64).add(i) = into_float(v);
+                return;
+            }
+        }
         self.0.store_field(rt, i, v);
     }
 
@@ -233,6 +246,11 @@ impl<T: IntoValue + FromValue> Array<T> {
     /// This function does not perform bounds checking
     #[inline]
     pub unsafe fn get_unchecked(&self, i: usize) -> T {
+        if let Some(from_float) = <T as FromValue>::from_float(Sealed(())) {
+            if self.is_double_array() {
+                return from_float(*((self.0).0 as *const f64).add(i));
+            }
+        }
         T::from_value(self.0.field(i))
     }
 
@@ -253,6 +271,92 @@ impl<T: IntoValue + FromValue> Array<T> {
     }
 }
 
+/// `FloatArray` is a borrowed view of an OCaml `float array`, values can be read without copying
+/// or converting the array. When OCaml is configured with `-no-flat-float-array` the elements are
+/// boxed, in that case `as_slice` returns `None` but the other methods still work.
+///
+/// `&[f64]` doesn't implement `FromValue` since it can't borrow boxed elements, take a
+/// `FloatArray` and call `as_slice` instead
+#[derive(Clone, Copy, PartialEq)]
+#[repr(transparent)]
+pub struct FloatArray<'a>(Value, PhantomData<&'a [f64]>);
+
+unsafe impl<'a> IntoValue for FloatArray<'a> {
+    fn into_value(self, _rt: &Runtime) -> Value {
+        self.0
+    }
+}
+
+unsafe impl<'a> FromValue for FloatArray<'a> {
+    fn from_value(value: Value) -> Self {
+        FloatArray(value, PhantomData)
+    }
+}
+
+impl<'a> FloatArray<'a> {
+    /// Array length
+    pub fn len(self) -> usize {
+        unsafe { sys::caml_array_length((self.0).0) }
+    }
+
+    /// Returns true when the array is empty
+    pub fn is_empty(self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns true when the elements are stored unboxed in a `Tag::DOUBLE_ARRAY` block
+    pub fn is_flat(self) -> bool {
+        unsafe { self.0.tag() == crate::Tag::DOUBLE_ARRAY }
+    }
+
+    /// Get array index
+    pub fn get(self, i: usize) -> Option<f64> {
+        if i >= self.len() {
+            return None;
+        }
+        Some(unsafe { self.get_unchecked(i) })
+    }
+
+    /// Get array index without bounds checking
+    ///
+    /// # Safety
+    ///
+    /// This function does not perform bounds checking
+    #[inline]
+    pub unsafe fn get_unchecked(self, i: usize) -> f64 {
+        if self.is_flat() {
+            *((self.0).0 as *const f64).add(i)
+        } else {
+            self.0.field(i)
+        }
+    }
+
+    /// Array as slice, this returns `None` when the elements are boxed
+    pub fn as_slice(self) -> Option<&'a [f64]> {
+        let len = self.len();
+        if len == 0 {
+            return Some(&[]);
+        }
+
+        if !self.is_flat() {
+            return None;
+        }
+
+        Some(unsafe { slice::from_raw_parts((self.0).0 as *const f64, len) })
+    }
+
+    /// Iterate over the elements of the array
+    pub fn iter(self) -> impl Iterator<Item = f64> + 'a {
+        (0..self.len()).map(move |i| unsafe { self.get_unchecked(i) })
+    }
+
+    /// Array as `Vec`
+    #[cfg(not(feature = "no-std"))]
+    pub fn to_vec(self) -> Vec<f64> {
+        self.iter().collect()
+    }
+}
+
 /// `List<A>` wraps an OCaml `'a list` without converting it to Rust, this introduces no
 /// additional overhead compared to a `Value` type
 #[derive(Clone, Copy, PartialEq)]
@@ -379,6 +483,67 @@ impl<'a, T: IntoValue + FromValue> Iterator for ListIterator<T> {
     }
 }
 
+macro_rules! closure_type {
+    ($(#[$meta:meta])* $name:ident($($arg:ident: $t:ident),*)) => {
+        $(#[$meta])*
+        #[repr(transparent)]
+        pub struct $name<$($t,)* R>(Value, PhantomData<fn($($t),*) -> R>);
+
+        impl<$($t,)* R> Clone for $name<$($t,)* R> {
+            fn clone(&self) -> Self {
+                *self
+            }
+        }
+
+        impl<$($t,)* R> Copy for $name<$($t,)* R> {}
+
+        unsafe impl<$($t,)* R> IntoValue for $name<$($t,)* R> {
+            fn into_value(self, _rt: &Runtime) -> Value {
+                self.0
+            }
+        }
+
+        unsafe impl<$($t,)* R> FromValue for $name<$($t,)* R> {
+            fn from_value(value: Value) -> Self {
+                if unsafe { !value.is_block() || value.tag() != crate::Tag::CLOSURE } {
+                    panic!("ocaml ffi: trying to convert a value which is not a closure to {}", stringify!($name))
+                }
+                $name(value, PhantomData)
+            }
+        }
+
+        impl<$($t: IntoValue,)* R: FromValue> $name<$($t,)* R> {
+            /// Call the closure, converting the arguments using `IntoValue` and the result using
+            /// `FromValue`. OCaml exceptions are returned as errors
+            pub fn call(&self, rt: &Runtime, $($arg: $t),*) -> Result<R, Error> {
+                let args = [$($arg.into_value(rt)),*];
+                unsafe { self.0.call_n(rt, args) }.map(R::from_value)
+            }
+        }
+    };
+}
+
+closure_type!(
+    /// `Fn1<A, R>` wraps an OCaml closure of type `'a -> 'r`
+    Fn1(a: A)
+);
+closure_type!(
+    /// `Fn2<A, B, R>` wraps an OCaml closure of type `'a -> 'b -> 'r`
+    Fn2(a: A, b: B)
+);
+closure_type!(
+    /// `Fn3<A, B, C, R>` wraps an OCaml closure of type `'a -> 'b -> 'c -> 'r`
+    Fn3(a: A, b: B, c: C)
+);
+closure_type!(
+    /// `Fn4<A, B, C, D, R>` wraps an OCaml closure of type `'a -> 'b -> 'c -> 'd -> 'r`
+    Fn4(a: A, b: B, c: C, d: D)
+);
+closure_type!(
+    /// `Fn5<A, B, C, D, E, R>` wraps an OCaml closure of type `'a -> 'b -> 'c -> 'd -> 'e -> 'r`
+    Fn5(a: A, b: B, c: C, d: D, e: E)
+);
+
 /// `bigarray` contains wrappers for OCaml `Bigarray` values. These types can be used to transfer arrays of numbers between Rust
 /// and OCaml directly without the allocation overhead of an `array` or `list`
 pub mod bigarray {
@@ -415,6 +580,59 @@ pub mod bigarray {
     make_kind!(i64, INT64);
     make_kind!(i32, INT32);
     make_kind!(char, CHAR);
+    make_kind!(isize, NATIVE_INT);
+    make_kind!(CamlInt, CAML_INT);
+    make_kind!(Complex<f32>, COMPLEX32);
+    make_kind!(Complex<f64>, COMPLEX64);
+
+    /// Element type of `Bigarray.int` arrays. Items are stored as native integers without the OCaml
+    /// tag bit, so only values that fit in an OCaml `int` can be read back without truncation
+    #[repr(transparent)]
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct CamlInt(pub crate::Int);
+
+    impl From<crate::Int> for CamlInt {
+        fn from(x: crate::Int) -> CamlInt {
+            CamlInt(x)
+        }
+    }
+
+    impl From<CamlInt> for crate::Int {
+        fn from(x: CamlInt) -> crate::Int {
+            x.0
+        }
+    }
+
+    /// Element type of `Bigarray.complex32` and `Bigarray.complex64` arrays
+    #[repr(C)]
+    #[derive(Clone, Copy, Debug, Default, PartialEq)]
+    pub struct Complex<T> {
+        /// Real part
+        pub re: T,
+        /// Imaginary part
+        pub im: T,
+    }
+
+    impl<T> Complex<T> {
+        /// Create a new complex number
+        pub const fn new(re: T, im: T) -> Complex<T> {
+            Complex { re, im }
+        }
+    }
+
+    #[cfg(feature = "num-complex")]
+    impl<T> From<num_complex::Complex<T>> for Complex<T> {
+        fn from(x: num_complex::Complex<T>) -> Complex<T> {
+            Complex { re: x.re, im: x.im }
+        }
+    }
+
+    #[cfg(feature = "num-complex")]
+    impl<T> From<Complex<T>> for num_complex::Complex<T> {
+        fn from(x: Complex<T>) -> num_complex::Complex<T> {
+            num_complex::Complex::new(x.re, x.im)
+        }
+    }
 
     /// OCaml Bigarray.Array1 type, this introduces no
     /// additional overhead compared to a `Value` type
@@ -422,10 +640,16 @@ pub mod bigarray {
     #[derive(Clone, Copy, PartialEq)]
     pub struct Array1<T>(Value, PhantomData<T>);
 
-    unsafe impl<T> crate::FromValue for Array1<T> {
+    unsafe impl<T: Kind> crate::FromValue for Array1<T> {
         fn from_value(value: Value) -> Array1<T> {
             unsafe { Array1(Value::new(value.0), PhantomData) }
         }
+
+        /// Checks the element kind and number of dimensions of the bigarray
+        #[cfg(not(feature = "no-std"))]
+        fn from_value_checked(value: Value) -> Result<Array1<T>, crate::Error> {
+            Ok(crate::TryFromValue::try_from_value(value)?)
+        }
     }
 
     unsafe impl<T> crate::IntoValue for Array1<T> {
@@ -465,6 +689,22 @@ pub mod bigarray {
             arr
         }
 
+        /// Convert a `Vec` to an OCaml Bigarray without copying it, see `Array1::from_boxed_slice`.
+        /// The vector is reallocated when its capacity is larger than its length
+        #[cfg(not(feature = "no-std"))]
+        pub fn from_vec(rt: &Runtime, data: Vec<T>) -> Array1<T> {
+            Array1::from_boxed_slice(rt, data.into_boxed_slice())
+        }
+
+        /// Convert a boxed slice to an OCaml Bigarray without copying it, the bigarray takes
+        /// ownership of `data` and it's dropped once the array and all arrays sharing its data
+        /// have been garbage collected
+        #[cfg(not(feature = "no-std"))]
+        pub fn from_boxed_slice(rt: &Runtime, data: Box<[T]>) -> Array1<T> {
+            let len = data.len();
+            Array1(alloc_owned(rt, data, &[len], Layout::C), PhantomData)
+        }
+
         /// Create a new OCaml `Bigarray.Array1` with the given type and size
         pub fn create(rt: &Runtime, n: Size) -> Array1<T> {
             let x = crate::frame!(rt: (x) {
@@ -513,22 +753,553 @@ pub mod bigarray {
         }
     }
 
+    /// Bigarray memory layout
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum Layout {
+        /// Row major, `Bigarray.c_layout`
+        C,
+        /// Column major, `Bigarray.fortran_layout`
+        Fortran,
+    }
+
+    impl Layout {
+        fn flags(self) -> i32 {
+            match self {
+                Layout::C => bigarray::Layout::C_LAYOUT as i32,
+                Layout::Fortran => bigarray::Layout::FORTRAN_LAYOUT as i32,
+            }
+        }
+    }
+
+    /// Returns the layout of the bigarray `v`
+    pub(crate) fn layout_of(v: Value) -> Layout {
+        let ba = unsafe { v.custom_ptr_val::<bigarray::Bigarray>() };
+        let flags = unsafe { (*ba).flags } & bigarray::LAYOUT_MASK;
+        if flags == bigarray::Layout::FORTRAN_LAYOUT as sys::Intnat {
+            Layout::Fortran
+        } else {
+            Layout::C
+        }
+    }
+
+    /// Returns the dimensions of the bigarray `v`
+    pub(crate) fn dims_of<'a>(v: Value) -> &'a [usize] {
+        unsafe {
+            let ba = v.custom_ptr_val::<bigarray::Bigarray>();
+            slice::from_raw_parts((*ba).dim.as_ptr() as *const usize, (*ba).num_dims as usize)
+        }
+    }
+
+    /// Converts `dims` to the dimension array passed to `caml_ba_alloc`
+    fn dims_array(dims: &[usize]) -> [sys::Intnat; bigarray::MAX_NUM_DIMS] {
+        let mut dim = [0 as sys::Intnat; bigarray::MAX_NUM_DIMS];
+        for (d, n) in dim.iter_mut().zip(dims) {
+            *d = *n as sys::Intnat;
+        }
+        dim
+    }
+
+    /// Proxy shared by a bigarray that owns a Rust allocation or a file mapping and all of its
+    /// sub-arrays, `free` releases `data` once the last array has been garbage collected
+    #[cfg(not(feature = "no-std"))]
+    #[repr(C)]
+    struct OwnedProxy {
+        proxy: bigarray::BigarrayProxy,
+        free: unsafe fn(bigarray::Data, usize),
+    }
+
+    #[cfg(not(feature = "no-std"))]
+    unsafe fn free_owned<T>(data: bigarray::Data, len: usize) {
+        drop(Box::from_raw(core::ptr::slice_from_raw_parts_mut(
+            data as *mut T,
+            len,
+        )))
+    }
+
+    #[cfg(not(feature = "no-std"))]
+    unsafe extern "C" fn finalize_owned(v: sys::Value) {
+        let ba = Value::new(v).custom_ptr_val::<bigarray::Bigarray>();
+        let proxy = (*ba).proxy as *mut OwnedProxy;
+        (*proxy).proxy.refcount -= 1;
+        if (*proxy).proxy.refcount == 0 {
+            let proxy = Box::from_raw(proxy);
+            (proxy.free)(proxy.proxy.data, proxy.proxy.size);
+        }
+    }
+
+    /// Custom operations for bigarrays that own a Rust allocation or a file mapping, these only
+    /// differ from the default bigarray operations by the finalizer. The OCaml runtime copies the
+    /// operations of a bigarray to its sub-arrays, so the data is only released once all of them
+    /// have been garbage collected. This is a `static` since bigarrays keep a pointer to it
+    #[cfg(not(feature = "no-std"))]
+    static OWNED_OPS: OwnedOps = OwnedOps(sys::custom_operations {
+        identifier: "_bigarr02\0".as_ptr() as *const sys::Char,
+        finalize: Some(finalize_owned),
+        compare: Some(bigarray::caml_ba_compare),
+        hash: Some(bigarray::caml_ba_hash),
+        serialize: Some(bigarray::caml_ba_serialize),
+        deserialize: Some(bigarray::caml_ba_deserialize),
+        compare_ext: None,
+        fixed_length: core::ptr::null(),
+    });
+
+    /// `custom_operations` contains raw pointers, which aren't `Sync`, but it's never modified
+    #[cfg(not(feature = "no-std"))]
+    struct OwnedOps(sys::custom_operations);
+
+    #[cfg(not(feature = "no-std"))]
+    unsafe impl Sync for OwnedOps {}
+
+    /// Allocate a bigarray with `OWNED_OPS` that releases `data` using `free`
+    #[cfg(not(feature = "no-std"))]
+    fn alloc_with_proxy(
+        rt: &Runtime,
+        flags: i32,
+        dims: &[usize],
+        data: bigarray::Data,
+        proxy: OwnedProxy,
+    ) -> Value {
+        let dim = dims_array(dims);
+        let proxy = Box::into_raw(Box::new(proxy));
+        let x = crate::frame!(rt: (x) {
+            x = unsafe {
+                Value::new(bigarray::caml_ba_alloc(
+                    flags,
+                    dims.len() as i32,
+                    data,
+                    dim.as_ptr(),
+                ))
+            };
+            x
+        });
+
+        unsafe {
+            // The first field of a custom block points to its custom operations
+            *(x.0 as *mut *const sys::custom_operations) = &OWNED_OPS.0;
+            (*x.custom_ptr_val_mut::<bigarray::Bigarray>()).proxy = proxy as *const _;
+        }
+        x
+    }
+
+    /// Allocate a bigarray that takes ownership of `data`, the number of items must already have
+    /// been checked against `dims`
+    #[cfg(not(feature = "no-std"))]
+    fn alloc_owned<T: Kind>(rt: &Runtime, data: Box<[T]>, dims: &[usize], layout: Layout) -> Value {
+        let len = data.len();
+        let data = Box::into_raw(data) as *mut T as bigarray::Data;
+        let proxy = OwnedProxy {
+            proxy: bigarray::BigarrayProxy {
+                refcount: 1,
+                data,
+                size: len as sys::Uintnat,
+            },
+            free: free_owned::<T>,
+        };
+        let flags = T::kind() | layout.flags() | bigarray::Managed::MANAGED as i32;
+        alloc_with_proxy(rt, flags, dims, data, proxy)
+    }
+
+    #[cfg(all(unix, not(feature = "no-std")))]
+    mod mmap {
+        use crate::sys::bigarray::Data;
+
+        pub unsafe fn unmap(data: Data, len: usize) {
+            if len > 0 {
+                libc::munmap(data, len);
+            }
+        }
+    }
+
+    /// How `ArrayN::map_file` maps a file
+    #[cfg(all(unix, not(feature = "no-std")))]
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum MapMode {
+        /// The pages are mapped read-only, the file only needs to be opened for reading and isn't
+        /// grown. Writing to the array, from Rust or OCaml, crashes the program
+        ReadOnly,
+        /// Changes are written back to the file and are visible to other processes mapping it,
+        /// the file has to be opened for reading and writing
+        Shared,
+        /// Changes are private to the array, the file only needs to be opened for reading unless
+        /// it has to be grown
+        Private,
+    }
+
+    impl<T> Array1<T> {
+        /// Returns the layout of `self`
+        pub fn layout(&self) -> Layout {
+            layout_of(self.0)
+        }
+    }
+
+    /// OCaml `Bigarray.Genarray` type, the number of dimensions and the layout are only known at
+    /// runtime. This introduces no additional overhead compared to a `Value` type
+    #[repr(transparent)]
+    #[derive(Clone, Copy, PartialEq)]
+    pub struct ArrayN<T>(Value, PhantomData<T>);
+
+    unsafe impl<T: Kind> crate::FromValue for ArrayN<T> {
+        fn from_value(value: Value) -> ArrayN<T> {
+            unsafe { ArrayN(Value::new(value.0), PhantomData) }
+        }
+
+        /// Checks the element kind and number of dimensions of the bigarray
+        #[cfg(not(feature = "no-std"))]
+        fn from_value_checked(value: Value) -> Result<ArrayN<T>, crate::Error> {
+            Ok(crate::TryFromValue::try_from_value(value)?)
+        }
+    }
+
+    unsafe impl<T> crate::IntoValue for ArrayN<T> {
+        fn into_value(self, _rt: &Runtime) -> Value {
+            self.0
+        }
+    }
+
+    impl<T: Copy + Kind> ArrayN<T> {
+        /// Create a new OCaml `Bigarray.Genarray` with the given type, dimensions and layout, an
+        /// error is returned when there are more than `sys::bigarray::MAX_NUM_DIMS` dimensions
+        pub fn create(rt: &Runtime, dims: &[usize], layout: Layout) -> Result<ArrayN<T>, Error> {
+            if dims.len() > bigarray::MAX_NUM_DIMS {
+                return Err(
+                    CamlError::InvalidArgument("too many bigarray dimensions".into()).into(),
+                );
+            }
+
+            let dim = dims_array(dims);
+            let n: usize = dims.iter().product();
+            let x = crate::frame!(rt: (x) {
+                let data = unsafe { bigarray::malloc(n * mem::size_of::<T>()) };
+                x = unsafe {
+                    Value::new(bigarray::caml_ba_alloc(
+                        T::kind() | layout.flags() | bigarray::Managed::MANAGED as i32,
+                        dims.len() as i32,
+                        data,
+                        dim.as_ptr(),
+                    ))
+                };
+                x
+            });
+            Ok(ArrayN(x, PhantomData))
+        }
+
+        /// Create an OCaml `Bigarray.Genarray` with the given dimensions and layout that takes
+        /// ownership of `data` without copying it, see `ArrayN::from_boxed_slice`
+        #[cfg(not(feature = "no-std"))]
+        pub fn from_vec(
+            rt: &Runtime,
+            data: Vec<T>,
+            dims: &[usize],
+            layout: Layout,
+        ) -> Result<ArrayN<T>, Error> {
+            ArrayN::from_boxed_slice(rt, data.into_boxed_slice(), dims, layout)
+        }
+
+        /// Create an OCaml `Bigarray.Genarray` with the given dimensions and layout that takes
+        /// ownership of `data` without copying it. The data is dropped once the array and all
+        /// arrays sharing its data have been garbage collected. An error is returned when the number
+        /// of items doesn't match `dims`
+        #[cfg(not(feature = "no-std"))]
+        pub fn from_boxed_slice(
+            rt: &Runtime,
+            data: Box<[T]>,
+            dims: &[usize],
+            layout: Layout,
+        ) -> Result<ArrayN<T>, Error> {
+            if dims.len() > bigarray::MAX_NUM_DIMS {
+                return Err(
+                    CamlError::InvalidArgument("too many bigarray dimensions".into()).into(),
+                );
+            }
+
+            if dims.iter().product::<usize>() != data.len() {
+                return Err(CamlError::InvalidArgument("bigarray size mismatch".into()).into());
+            }
+
+            Ok(ArrayN(alloc_owned(rt, data, dims, layout), PhantomData))
+        }
+
+        /// Map `file` into memory starting at byte offset `pos`, like `Unix.map_file`, see
+        /// `MapMode` for the ways the file can be mapped. The file is grown when it's too small
+        /// for the given dimensions, except with `MapMode::ReadOnly` which returns an error.
+        ///
+        /// The major dimension (the first one for C layout, the last one for Fortran layout) can be
+        /// set to `None` to infer it from the size of the file. The file is unmapped once the array
+        /// and all arrays sharing its data have been garbage collected
+        #[cfg(all(unix, not(feature = "no-std")))]
+        pub fn map_file(
+            rt: &Runtime,
+            file: &std::fs::File,
+            pos: u64,
+            mode: MapMode,
+            dims: &[Option<usize>],
+            layout: Layout,
+        ) -> Result<ArrayN<T>, Error> {
+            use core::convert::TryFrom;
+            use std::os::unix::io::AsRawFd;
+
+            if dims.is_empty() || dims.len() > bigarray::MAX_NUM_DIMS {
+                return Err(
+                    CamlError::InvalidArgument("bad number of bigarray dimensions".into()).into(),
+                );
+            }
+
+            let major = match layout {
+                Layout::C => 0,
+                Layout::Fortran => dims.len() - 1,
+            };
+            if dims
+                .iter()
+                .enumerate()
+                .any(|(i, d)| d.is_none() && i != major)
+            {
+                return Err(CamlError::InvalidArgument(
+                    "only the major bigarray dimension can be inferred".into(),
+                )
+                .into());
+            }
+
+            let too_large = || CamlError::InvalidArgument("bigarray is too large".into());
+            let infer = dims[major].is_none();
+            let mut size = dims
+                .iter()
+                .flatten()
+                .try_fold(mem::size_of::<T>() as u64, |size, d| {
+                    size.checked_mul(*d as u64)
+                })
+                .ok_or_else(too_large)?;
+            let mut dims: Vec<usize> = dims.iter().map(|d| d.unwrap_or(0)).collect();
+            let file_size = file.metadata()?.len();
+            if infer {
+                if file_size < pos {
+                    return Err(CamlError::InvalidArgument(
+                        "file position is past the end of the file".into(),
+                    )
+                    .into());
+                }
+
+                let data_size = file_size - pos;
+                let count = data_size.checked_div(size).unwrap_or(0);
+                if count * size != data_size {
+                    return Err(CamlError::InvalidArgument(
+                        "file size doesn't match bigarray dimensions".into(),
+                    )
+                    .into());
+                }
+                dims[major] = count as usize;
+                size = data_size;
+            } else {
+                let end = pos.checked_add(size).ok_or_else(too_large)?;
+                if file_size < end {
+                    if mode == MapMode::ReadOnly {
+                        return Err(CamlError::InvalidArgument(
+                            "file is too small for a read-only mapping".into(),
+                        )
+                        .into());
+                    }
+                    file.set_len(end)?;
+                }
+            }
+
+            let delta = pos % unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as u64;
+            let len = size
+                .checked_add(delta)
+                .and_then(|len| usize::try_from(len).ok())
+                .ok_or_else(too_large)?;
+            let offset = libc::off_t::try_from(pos - delta).map_err(|_| too_large())?;
+            let (base, data) = if len == 0 {
+                let data = core::ptr::NonNull::<T>::dangling().as_ptr() as bigarray::Data;
+                (core::ptr::null_mut(), data)
+            } else {
+                let (prot, flags) = match mode {
+                    MapMode::ReadOnly => (libc::PROT_READ, libc::MAP_SHARED),
+                    MapMode::Shared => (libc::PROT_READ | libc::PROT_WRITE, libc::MAP_SHARED),
+                    MapMode::Private => (libc::PROT_READ | libc::PROT_WRITE, libc::MAP_PRIVATE),
+                };
+                let base = unsafe {
+                    libc::mmap(
+                        core::ptr::null_mut(),
+                        len,
+                        prot,
+                        flags,
+                        file.as_raw_fd(),
+                        offset,
+                    )
+                };
+                if base == libc::MAP_FAILED {
+                    return Err(std::io::Error::last_os_error().into());
+                }
+                (base, unsafe { (base as *mut u8).add(delta as usize) }
+                    as bigarray::Data)
+            };
+
+            let proxy = OwnedProxy {
+                proxy: bigarray::BigarrayProxy {
+                    refcount: 1,
+                    data: base,
+                    size: len,
+                },
+                free: mmap::unmap,
+            };
+            let flags = T::kind() | layout.flags() | bigarray::Managed::MAPPED_FILE as i32;
+            Ok(ArrayN(
+                alloc_with_proxy(rt, flags, &dims, data, proxy),
+                PhantomData,
+            ))
+        }
+
+        /// Returns the dimensions of `self`
+        pub fn dims(&self) -> &[usize] {
+            dims_of(self.0)
+        }
+
+        /// Returns the layout of `self`
+        pub fn layout(&self) -> Layout {
+            layout_of(self.0)
+        }
+
+        /// Returns the number of items in `self`
+        pub fn len(&self) -> Size {
+            self.dims().iter().product()
+        }
+
+        /// Returns true when `self.len() == 0`
+        pub fn is_empty(&self) -> bool {
+            self.len() == 0
+        }
+
+        /// Get underlying data as Rust slice, items are stored in row major order for C layout and
+        /// column major order for Fortran layout
+        pub fn data(&self) -> &[T] {
+            unsafe {
+                let ba = self.0.custom_ptr_val::<bigarray::Bigarray>();
+                slice::from_raw_parts((*ba).data as *const T, self.len())
+            }
+        }
+
+        /// Get underlying data as mutable Rust slice
+        pub fn data_mut(&mut self) -> &mut [T] {
+            unsafe {
+                let ba = self.0.custom_ptr_val::<bigarray::Bigarray>();
+                slice::from_raw_parts_mut((*ba).data as *mut T, self.len())
+            }
+        }
+
+        /// Returns the `len` items starting at `offset` along the first dimension for C layout or
+        /// the last dimension for Fortran layout. The result shares data with `self`, `offset`
+        /// starts at 0 for both layouts
+        pub fn sub(&self, _rt: &Runtime, offset: usize, len: usize) -> Result<ArrayN<T>, Error> {
+            let dims = self.dims();
+            let (dim, base) = match self.layout() {
+                Layout::C => (dims.first(), 0),
+                Layout::Fortran => (dims.last(), 1),
+            };
+            match (dim, offset.checked_add(len)) {
+                (Some(dim), Some(end)) if end <= *dim => (),
+                _ => return Err(CamlError::ArrayBoundError.into()),
+            }
+
+            let x = unsafe {
+                bigarray::caml_ba_sub(
+                    self.0 .0,
+                    Value::int((offset + base) as crate::Int).0,
+                    Value::int(len as crate::Int).0,
+                )
+            };
+            Ok(ArrayN(unsafe { Value::new(x) }, PhantomData))
+        }
+
+        /// Returns the sub-array obtained by fixing the first `index.len()` indices for C layout or
+        /// the last `index.len()` indices for Fortran layout. The result shares data with `self`,
+        /// indices start at 0 for both layouts
+        #[cfg(not(feature = "no-std"))]
+        pub fn slice(&self, rt: &Runtime, index: &[usize]) -> Result<ArrayN<T>, Error> {
+            let dims = self.dims();
+            if index.len() > dims.len() {
+                return Err(CamlError::InvalidArgument("too many bigarray indices".into()).into());
+            }
+
+            let (fixed, base) = match self.layout() {
+                Layout::C => (&dims[..index.len()], 0),
+                Layout::Fortran => (&dims[dims.len() - index.len()..], 1),
+            };
+            if index.iter().zip(fixed).any(|(i, dim)| i >= dim) {
+                return Err(CamlError::ArrayBoundError.into());
+            }
+
+            // `self` is rooted while the index array is allocated
+            let ba = crate::Root::new(rt, self.0);
+            let index: Vec<crate::Int> = index.iter().map(|i| (i + base) as crate::Int).collect();
+            let index = index.into_value(rt);
+            let x = unsafe { bigarray::caml_ba_slice(ba.value(rt).0, index.0) };
+            Ok(ArrayN(unsafe { Value::new(x) }, PhantomData))
+        }
+
+        /// Returns an array with the same data as `self` and new dimensions, the number of items
+        /// can't change
+        #[cfg(not(feature = "no-std"))]
+        pub fn reshape(&self, rt: &Runtime, dims: &[usize]) -> Result<ArrayN<T>, Error> {
+            if dims.len() > bigarray::MAX_NUM_DIMS {
+                return Err(
+                    CamlError::InvalidArgument("too many bigarray dimensions".into()).into(),
+                );
+            }
+
+            if dims.iter().product::<usize>() != self.len() {
+                return Err(CamlError::InvalidArgument("bigarray size mismatch".into()).into());
+            }
+
+            // `self` is rooted while the dimension array is allocated
+            let ba = crate::Root::new(rt, self.0);
+            let dims: Vec<crate::Int> = dims.iter().map(|d| *d as crate::Int).collect();
+            let dims = dims.into_value(rt);
+            let x = unsafe { bigarray::caml_ba_reshape(ba.value(rt).0, dims.0) };
+            Ok(ArrayN(unsafe { Value::new(x) }, PhantomData))
+        }
+    }
+
     #[cfg(all(feature = "bigarray-ext", not(feature = "no-std")))]
     pub use super::bigarray_ext::*;
 }
 
 #[cfg(all(feature = "bigarray-ext", not(feature = "no-std")))]
 pub(crate) mod bigarray_ext {
-    use ndarray::{ArrayView2, ArrayView3, ArrayViewMut2, ArrayViewMut3, Dimension};
+    use ndarray::{
+        ArrayView2, ArrayView3, ArrayViewD, ArrayViewMut2, ArrayViewMut3, ArrayViewMutD, Dimension,
+        IxDyn, ShapeBuilder,
+    };
 
     use core::{marker::PhantomData, mem, ptr, slice};
 
     use crate::{
-        bigarray::Kind,
+        bigarray::{layout_of, ArrayN, Kind, Layout},
         sys::{self, bigarray},
-        FromValue, IntoValue, Runtime, Value,
+        Error, FromValue, IntoValue, Runtime, Value,
     };
 
+    impl<T: Copy + Kind> ArrayN<T> {
+        /// Returns array view, using the strides of the layout of `self`
+        pub fn view(&self) -> ArrayViewD<'_, T> {
+            let shape = IxDyn(self.dims()).set_f(self.layout() == Layout::Fortran);
+            unsafe { ArrayViewD::from_shape_ptr(shape, self.data().as_ptr()) }
+        }
+
+        /// Returns mutable array view, using the strides of the layout of `self`
+        pub fn view_mut(&mut self) -> ArrayViewMutD<'_, T> {
+            let shape = IxDyn(self.dims()).set_f(self.layout() == Layout::Fortran);
+            unsafe { ArrayViewMutD::from_shape_ptr(shape, self.data_mut().as_mut_ptr()) }
+        }
+
+        /// Create ArrayN with C layout from ndarray
+        pub fn from_ndarray(rt: &Runtime, data: ndarray::ArrayD<T>) -> Result<ArrayN<T>, Error> {
+            let mut array = ArrayN::create(rt, data.shape(), Layout::C)?;
+            for (dst, src) in array.data_mut().iter_mut().zip(data.iter()) {
+                *dst = *src;
+            }
+            Ok(array)
+        }
+    }
+
     /// OCaml Bigarray.Array2 type, this introduces no
     /// additional overhead compared to a `Value` type
     #[repr(transparent)]
@@ -539,13 +1310,20 @@ pub(crate) mod bigarray_ext {
         /// Returns array view
         pub fn view(&self) -> ArrayView2<T> {
             let ba = unsafe { self.0.custom_ptr_val::<bigarray::Bigarray>() };
-            unsafe { ArrayView2::from_shape_ptr(self.shape(), (*ba).data as *const T) }
+            let shape = self.shape().set_f(self.layout() == Layout::Fortran);
+            unsafe { ArrayView2::from_shape_ptr(shape, (*ba).data as *const T) }
         }
 
         /// Returns mutable array view
         pub fn view_mut(&mut self) -> ArrayViewMut2<T> {
             let ba = unsafe { self.0.custom_ptr_val::<bigarray::Bigarray>() };
-            unsafe { ArrayViewMut2::from_shape_ptr(self.shape(), (*ba).data as *mut T) }
+            let shape = self.shape().set_f(self.layout() == Layout::Fortran);
+            unsafe { ArrayViewMut2::from_shape_ptr(shape, (*ba).data as *mut T) }
+        }
+
+        /// Returns the layout of `self`
+        pub fn layout(&self) -> Layout {
+            layout_of(self.0)
         }
 
         /// Returns the shape of `self`
@@ -571,10 +1349,15 @@ pub(crate) mod bigarray_ext {
         }
     }
 
-    unsafe impl<T> FromValue for Array2<T> {
+    unsafe impl<T: Kind> FromValue for Array2<T> {
         fn from_value(value: Value) -> Array2<T> {
             unsafe { Array2(Value::new(value.0), PhantomData) }
         }
+
+        /// Checks the element kind and number of dimensions of the bigarray
+        fn from_value_checked(value: Value) -> Result<Array2<T>, crate::Error> {
+            Ok(crate::TryFromValue::try_from_value(value)?)
+        }
     }
 
     unsafe impl<T> IntoValue for Array2<T> {
@@ -624,13 +1407,20 @@ pub(crate) mod bigarray_ext {
         /// Returns array view
         pub fn view(&self) -> ArrayView3<T> {
             let ba = unsafe { self.0.custom_ptr_val::<bigarray::Bigarray>() };
-            unsafe { ArrayView3::from_shape_ptr(self.shape(), (*ba).data as *const T) }
+            let shape = self.shape().set_f(self.layout() == Layout::Fortran);
+            unsafe { ArrayView3::from_shape_ptr(shape, (*ba).data as *const T) }
         }
 
         /// Returns mutable array view
         pub fn view_mut(&mut self) -> ArrayViewMut3<T> {
             let ba = unsafe { self.0.custom_ptr_val::<bigarray::Bigarray>() };
-            unsafe { ArrayViewMut3::from_shape_ptr(self.shape(), (*ba).data as *mut T) }
+            let shape = self.shape().set_f(self.layout() == Layout::Fortran);
+            unsafe { ArrayViewMut3::from_shape_ptr(shape, (*ba).data as *mut T) }
+        }
+
+        /// Returns the layout of `self`
+        pub fn layout(&self) -> Layout {
+            layout_of(self.0)
         }
 
         /// Returns the shape of `self`
@@ -656,10 +1446,15 @@ pub(crate) mod bigarray_ext {
         }
     }
 
-    unsafe impl<T> FromValue for Array3<T> {
+    unsafe impl<T: Kind> FromValue for Array3<T> {
         fn from_value(value: Value) -> Array3<T> {
             unsafe { Array3(Value::new(value.0), PhantomData) }
         }
+
+        /// Checks the element kind and number of dimensions of the bigarray
+        fn from_value_checked(value: Value) -> Result<Array3<T>, crate::Error> {
+            Ok(crate::TryFromValue::try_from_value(value)?)
+        }
     }
 
     unsafe impl<T> IntoValue for Array3<T> {
@@ -1,5 +1,13 @@
+/// Argument of the trait methods used to read and write unboxed float arrays, it can't be named
+/// outside of this crate so those methods can't be called or overridden by other crates
+pub struct Sealed(pub(crate) ());
+
+#[cfg(feature = "no-std")]
+pub use alloc::{borrow::Cow, fmt::format, string::String};
 #[cfg(feature = "no-std")]
 pub use cstr_core::CString;
 
 #[cfg(not(feature = "no-std"))]
 pub use std::ffi::CString;
+#[cfg(not(feature = "no-std"))]
+pub use std::{borrow::Cow, fmt::format, string::String};
@@ -1,5 +1,6 @@
-use crate::error::{CamlError, Error};
+use crate::error::Error;
 use crate::tag::Tag;
+use crate::util::Sealed;
 use crate::{sys, OCaml, OCamlRef, Runtime};
 
 /// Size is an alias for the platform specific integer type used to store size values
@@ -21,12 +22,42 @@ impl Clone for Value {
 pub unsafe trait IntoValue {
     /// Convert to OCaml value
     fn into_value(self, rt: &Runtime) -> Value;
+
+    #[doc(hidden)]
+    /// Conversion to the unboxed representation used by OCaml float arrays, only `f32` and `f64`
+    /// return `Some`
+    fn into_float(_: Sealed) -> Option<fn(Self) -> f64>
+    where
+        Self: Sized,
+    {
+        None
+    }
 }
 
 /// `FromValue` is used to convert from OCaml values to Rust types
 pub unsafe trait FromValue {
     /// Convert from OCaml value
     fn from_value(v: Value) -> Self;
+
+    #[doc(hidden)]
+    /// Conversion from the unboxed representation used by OCaml float arrays, only `f32` and
+    /// `f64` return `Some`
+    fn from_float(_: Sealed) -> Option<fn(f64) -> Self>
+    where
+        Self: Sized,
+    {
+        None
+    }
+
+    /// Convert from OCaml value, returning an error when a cheap check shows the value can't be
+    /// converted. This is used for `#[ocaml::func]` parameters, the default implementation calls
+    /// `from_value`
+    fn from_value_checked(v: Value) -> Result<Self, Error>
+    where
+        Self: Sized,
+    {
+        Ok(Self::from_value(v))
+    }
 }
 
 unsafe impl IntoValue for Value {
@@ -418,7 +449,7 @@ impl Value {
 
         if v.is_exception_result() {
             v = v.exception().unwrap();
-            Err(CamlError::Exception(v).into())
+            Err(Error::from_callback_exception(rt, v))
         } else {
             Ok(v)
         }
@@ -443,7 +474,7 @@ impl Value {
 
         if v.is_exception_result() {
             v = v.exception().unwrap();
-            Err(CamlError::Exception(v).into())
+            Err(Error::from_callback_exception(rt, v))
         } else {
             Ok(v)
         }
@@ -474,7 +505,7 @@ impl Value {
 
         if v.is_exception_result() {
             v = v.exception().unwrap();
-            Err(CamlError::Exception(v).into())
+            Err(Error::from_callback_exception(rt, v))
         } else {
             Ok(v)
         }
@@ -501,7 +532,7 @@ impl Value {
 
         if v.is_exception_result() {
             v = v.exception().unwrap();
-            Err(CamlError::Exception(v).into())
+            Err(Error::from_callback_exception(rt, v))
         } else {
             Ok(v)
         }
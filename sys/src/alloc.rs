@@ -15,6 +15,7 @@ extern "C" {
     pub fn caml_copy_string_array(arr: *const *const Char) -> Value;
     pub fn caml_is_double_array(v: Value) -> i32;
     pub fn caml_copy_double(double: f64) -> Value;
+    pub fn caml_alloc_float_array(len: Size) -> Value;
     pub fn caml_copy_int32(int: i32) -> Value; // defined in [ints.c]
     pub fn caml_copy_int64(int: i64) -> Value; // defined in [ints.c]
     pub fn caml_copy_nativeint(int: isize) -> Value; // defined in [ints.c]
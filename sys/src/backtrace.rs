@@ -0,0 +1,11 @@
+//! Exception backtraces, these are the primitives used by `Printexc`
+
+use crate::mlvalues::Value;
+
+extern "C" {
+    pub fn caml_record_backtrace(flag: Value) -> Value;
+    pub fn caml_backtrace_status(unit: Value) -> Value;
+    pub fn caml_get_exception_raw_backtrace(unit: Value) -> Value;
+    pub fn caml_convert_raw_backtrace(bt: Value) -> Value;
+    pub fn caml_get_exception_backtrace(unit: Value) -> Value;
+}
@@ -7,9 +7,9 @@ pub type Data = *mut c_void;
 
 #[repr(C)]
 pub struct BigarrayProxy {
-    refcount: Intnat,
-    data: Data,
-    size: Uintnat,
+    pub refcount: Intnat,
+    pub data: Data,
+    pub size: Uintnat,
 }
 
 #[repr(C)]
@@ -29,6 +29,18 @@ pub enum Managed {
     MANAGED_MASK = 0x600, /* Mask for "managed" bits in flags field */
 }
 
+/// Maximum number of dimensions of a bigarray
+pub const MAX_NUM_DIMS: usize = 16;
+
+#[allow(non_camel_case_types)]
+pub enum Layout {
+    C_LAYOUT = 0,           /* Row major, indices start at 0 */
+    FORTRAN_LAYOUT = 0x100, /* Column major, indices start at 1 */
+}
+
+/// Mask for layout in flags field
+pub const LAYOUT_MASK: Intnat = 0x100;
+
 #[allow(non_camel_case_types)]
 pub enum Kind {
     FLOAT32 = 0x00,    /* Single-precision floats */
@@ -49,7 +61,14 @@ pub enum Kind {
 
 extern "C" {
     pub fn malloc(size: usize) -> Data;
-    pub fn caml_ba_alloc(flags: i32, num_dims: i32, data: Data, dim: *const i32) -> Value;
+    pub fn caml_ba_alloc(flags: i32, num_dims: i32, data: Data, dim: *const Intnat) -> Value;
     pub fn caml_ba_alloc_dims(flags: i32, num_dims: i32, data: Data, ...) -> Value;
     pub fn caml_ba_byte_size(b: *const Bigarray) -> u32;
+    pub fn caml_ba_compare(v1: Value, v2: Value) -> i32;
+    pub fn caml_ba_hash(v: Value) -> Intnat;
+    pub fn caml_ba_serialize(v: Value, wsize_32: *mut Uintnat, wsize_64: *mut Uintnat);
+    pub fn caml_ba_deserialize(dst: *mut c_void) -> Uintnat;
+    pub fn caml_ba_sub(vb: Value, vofs: Value, vlen: Value) -> Value;
+    pub fn caml_ba_slice(vb: Value, vind: Value) -> Value;
+    pub fn caml_ba_reshape(vb: Value, vdim: Value) -> Value;
 }
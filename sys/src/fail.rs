@@ -18,4 +18,5 @@ extern "C" {
     pub fn caml_raise_not_found();
     pub fn caml_array_bound_error();
     pub fn caml_raise_sys_blocked_io();
+    pub fn caml_format_exception(exn: Value) -> *mut Char;
 }
@@ -26,6 +26,7 @@ mod mlvalues;
 #[macro_use]
 mod memory;
 mod alloc;
+mod backtrace;
 pub mod bigarray;
 mod callback;
 mod custom;
@@ -37,6 +38,7 @@ mod tag;
 pub use self::mlvalues::Value;
 pub use self::tag::Tag;
 pub use alloc::*;
+pub use backtrace::*;
 pub use callback::*;
 pub use custom::*;
 pub use fail::*;
@@ -105,4 +105,5 @@ extern "C" {
     pub fn caml_register_generational_global_root(value: *mut Value);
     pub fn caml_remove_generational_global_root(value: *mut Value);
     pub fn caml_modify_generational_global_root(value: *mut Value, newval: Value);
+    pub fn caml_stat_free(ptr: *mut core::ffi::c_void);
 }
@@ -26,3 +26,72 @@ external apply_range: (int list -> 'a) -> int -> int -> 'a = "apply_range"
 
 let%test "apply range 1" =
   Util.check_leaks (fun () -> apply_range (List.map (fun a  -> let () = Util.gc () in a + 1)) 0 10 = [1; 2; 3; 4; 5; 6; 7; 8; 9; 10])
+
+class counter init = object
+  val mutable n = init
+  method incr x = n <- n + x; n
+  method name = "counter"
+  method reset () = n <- 0
+end
+
+external counter_run: < incr: int -> int; name: string; reset: unit -> unit; .. > -> int -> string = "counter_run"
+
+let%test "object proxy" = Util.check_leaks (fun () ->
+  let c = new counter 1 in
+  let s = counter_run c 2 in
+  Util.gc ();
+  s = "counter 5 0 true"
+)
+
+external apply_typed: (int -> string -> string) -> int -> string = "apply_typed"
+external apply_checked: Obj.t -> float -> float = "apply_checked"
+
+let%test "typed closure" = Util.check_leaks (fun () ->
+  let s = apply_typed (fun n s -> let () = Util.gc () in s ^ string_of_int n) 1 in
+  s = "a12"
+)
+
+let%test "typed closure exception" = Util.check_leaks (fun () ->
+  try apply_typed (fun _ _ -> failwith "typed") 1 = ""
+  with Failure x -> x = "typed"
+)
+
+let%test "checked closure" = Util.check_leaks (fun () ->
+  apply_checked (Obj.repr (fun x -> x *. 2.)) 1.5 = 3.
+  && (try apply_checked (Obj.repr 1) 1. = 0. with Failure x -> x = "DecodeError(expected closure, got int)")
+)
+
+(* The closure trampoline is registered by the ocaml-rs.closure library *)
+external make_adder: int -> (int -> int) = "make_adder"
+external make_counter: unit -> (int -> int) = "make_counter"
+external make_greeter: string -> (string -> string) = "make_greeter"
+external make_reentrant: unit -> (Obj.t -> Obj.t) = "make_reentrant"
+
+let%test "rust closure" = Util.check_leaks (fun () ->
+  let l = List.map (make_adder 2) [1; 2; 3] in
+  Util.gc ();
+  l = [3; 4; 5]
+)
+
+let%test "rust closure state" = Util.check_leaks (fun () ->
+  let counter = make_counter () in
+  List.iter (fun x -> ignore (counter x)) [1; 2; 3];
+  Util.gc ();
+  counter 0 = 6
+)
+
+let%test "rust closure string" = Util.check_leaks (fun () ->
+  let greet = make_greeter "hello" in
+  let s = List.map greet ["a"; "b"] in
+  Util.gc ();
+  s = ["hello a"; "hello b"]
+)
+
+let%test "rust closure reentrant" = Util.check_leaks (fun () ->
+  let f = make_reentrant () in
+  let call () =
+    try ignore (f (Obj.repr f)); false
+    with Failure x -> x = "Rust closure called while it is already running"
+  in
+  call () && call ()
+)
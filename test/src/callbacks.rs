@@ -21,3 +21,65 @@ pub unsafe fn apply_range(f: Value, start: ocaml::Int, stop: ocaml::Int) -> Resu
 
     f.call(gc, l)
 }
+
+#[ocaml::object]
+pub trait Counter {
+    fn incr(&self, n: ocaml::Int) -> ocaml::Int;
+    fn name(&self) -> Result<String, Error>;
+    #[ocaml(name = "reset")]
+    fn clear(&self, unit: ());
+    fn missing(&self) -> Result<(), Error>;
+}
+
+#[ocaml::func]
+pub fn counter_run(c: CounterObject, n: ocaml::Int) -> Result<String, Error> {
+    c.incr(n);
+    let x = c.incr(n);
+    let name = c.name()?;
+    c.clear(());
+    let missing = c.missing().is_err();
+    Ok(format!("{} {} {} {}", name, x, c.incr(0), missing))
+}
+
+#[ocaml::func]
+pub fn apply_typed(
+    f: ocaml::Fn2<ocaml::Int, String, String>,
+    n: ocaml::Int,
+) -> Result<String, Error> {
+    let a = f.call(gc, n, "a".to_string())?;
+    f.call(gc, n + 1, a)
+}
+
+#[ocaml::func]
+pub fn apply_checked(f: Value, x: f64) -> Result<f64, Error> {
+    let f: ocaml::Fn1<f64, f64> = ocaml::TryFromValue::try_from_value(f)?;
+    f.call(gc, x)
+}
+
+#[ocaml::func]
+pub fn make_adder(n: ocaml::Int) -> Result<Value, Error> {
+    Value::closure(gc, move |x: ocaml::Int| x + n)
+}
+
+#[ocaml::func]
+pub fn make_counter() -> Result<Value, Error> {
+    let mut count = 0;
+    Value::closure(gc, move |x: ocaml::Int| {
+        count += x;
+        count
+    })
+}
+
+#[ocaml::func]
+pub fn make_greeter(greeting: String) -> Result<Value, Error> {
+    Value::closure(gc, move |name: String| format!("{} {}", greeting, name))
+}
+
+#[ocaml::func]
+pub fn make_reentrant() -> Result<Value, Error> {
+    // Calls its argument with itself, which calls the Rust closure again while it is running
+    Value::closure(gc, |f: Value| -> Result<Value, Error> {
+        let rt = unsafe { ocaml::Runtime::recover_handle() };
+        unsafe { f.call(rt, f) }
+    })
+}
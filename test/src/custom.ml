@@ -36,3 +36,18 @@ let%test "testing callback 2" = Util.check_leaks (fun () -> (
   Util.gc ();
   testing_callback_call c 5 = sin 5.0)
 )
+
+type handlers
+external handlers_alloc: unit -> handlers = "handlers_alloc"
+external handlers_add: handlers -> (int -> int) -> unit = "handlers_add"
+external handlers_run: handlers -> int -> int = "handlers_run"
+
+let%test "rooted handlers" = Util.check_leaks (fun () -> (
+  let h = handlers_alloc () in
+  let k = ref 3 in
+  handlers_add h (fun x -> x + !k);
+  Util.gc ();
+  handlers_add h (fun x -> x * 2);
+  Util.gc ();
+  handlers_run h 1 = 8)
+)
@@ -74,3 +74,35 @@ pub unsafe fn testing_callback_call(
 ) -> Result<ocaml::Value, ocaml::Error> {
     t.as_ref().func.call(gc, x)
 }
+
+#[derive(Default)]
+struct Handlers(Vec<ocaml::Root<ocaml::Fn1<ocaml::Int, ocaml::Int>>>);
+
+unsafe extern "C" fn handlers_finalize(a: Value) {
+    let t0 = ocaml::Pointer::<Handlers>::from_value(a);
+    t0.drop_in_place();
+}
+
+ocaml::custom_finalize!(Handlers, handlers_finalize);
+
+#[ocaml::func]
+pub fn handlers_alloc() -> Handlers {
+    Handlers::default()
+}
+
+#[ocaml::func]
+pub fn handlers_add(mut h: ocaml::Pointer<Handlers>, f: ocaml::Fn1<ocaml::Int, ocaml::Int>) {
+    h.as_mut().0.push(ocaml::Root::new(gc, f));
+}
+
+#[ocaml::func]
+pub fn handlers_run(
+    h: ocaml::Pointer<Handlers>,
+    x: ocaml::Int,
+) -> Result<ocaml::Int, ocaml::Error> {
+    let mut acc = x;
+    for f in &h.as_ref().0 {
+        acc = f.get(gc).call(gc, acc)?;
+    }
+    Ok(acc)
+}
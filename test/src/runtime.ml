@@ -33,6 +33,158 @@ let%test "raise failure" = try
   raise_failure ()
 with Failure e -> let () = Util.gc () in e = "An error"
 
+external raise_failure_fmt: string -> int -> unit = "raise_failure_fmt"
+external raise_invalid_argument: string -> unit = "raise_invalid_argument"
+external raise_sys_error: string -> unit = "raise_sys_error"
+
+let%test "raise failure fmt" = Util.check_leaks (fun () ->
+  try raise_failure_fmt "test.ml" 12; false
+  with Failure e -> let () = Util.gc () in e = "test.ml:12: unexpected token")
+
+let%test "raise invalid argument" = Util.check_leaks (fun () ->
+  try raise_invalid_argument "x"; false
+  with Invalid_argument e -> e = "bad argument: x")
+
+let%test "raise sys error" = Util.check_leaks (fun () ->
+  try raise_sys_error "missing.txt"; false
+  with Sys_error e -> e = "missing.txt: No such file or directory")
+
+external raise_predefined: string -> unit = "raise_predefined"
+external catch_predefined: (unit -> unit) -> string = "catch_predefined"
+
+let predefined = [
+  "Not_found", Not_found;
+  "Out_of_memory", Out_of_memory;
+  "Stack_overflow", Stack_overflow;
+  "End_of_file", End_of_file;
+  "Division_by_zero", Division_by_zero;
+  "Sys_blocked_io", Sys_blocked_io;
+  "Failure", Failure "Failure";
+  "Invalid_argument", Invalid_argument "Invalid_argument";
+  "Sys_error", Sys_error "Sys_error";
+  "Exit", Exit;
+  "Match_failure", Match_failure ("test.ml", 1, 2);
+  "Assert_failure", Assert_failure ("test.ml", 3, 4);
+  "Undefined_recursive_module", Undefined_recursive_module ("test.ml", 5, 6);
+]
+
+(* Exit is defined by the standard library, so it has to be registered before Rust can raise it *)
+let () = Callback.register_exception "Exit" Exit
+
+let%test "raise predefined exceptions" = Util.check_leaks (fun () ->
+  List.for_all (fun (name, exn) ->
+    try raise_predefined name; false with e -> e = exn) predefined)
+
+let%test "catch predefined exceptions" = Util.check_leaks (fun () ->
+  catch_predefined (fun () -> ()) = "ok" &&
+  catch_predefined (fun () -> failwith "x") = "Failure x" &&
+  catch_predefined (fun () -> raise (Sys_error "y")) = "Sys_error y" &&
+  catch_predefined (fun () -> raise (Match_failure ("a.ml", 1, 2))) = "Match_failure a.ml 1 2" &&
+  List.for_all (fun (name, exn) ->
+    let s = catch_predefined (fun () -> raise exn) in
+    String.length s >= String.length name && String.sub s 0 (String.length name) = name)
+    predefined)
+
+external exn_info: exn -> string * int * bool * string = "exn_info"
+external exn_message: exn -> string option = "exn_message"
+
+exception Custom of int * string
+
+let%test "exn info" = Util.check_leaks (fun () ->
+  List.for_all (fun (exn, arity, predefined) ->
+    exn_info exn = (Printexc.exn_slot_name exn, arity, predefined, Printexc.to_string exn))
+    [ Not_found, 0, true;
+      Failure "x", 1, true;
+      Sys_error "s", 1, true;
+      Exit, 0, false;
+      Custom (1, "a"), 2, false ])
+
+external call_exn_info: (unit -> unit) -> (string * int * bool * string) option = "call_exn_info"
+
+let%test "exn from call" = Util.check_leaks (fun () ->
+  call_exn_info (fun () -> ()) = None &&
+  List.for_all (fun exn ->
+    let info = call_exn_info (fun () -> raise exn) in
+    Util.gc ();
+    info = Some (Printexc.exn_slot_name exn, (if exn = Not_found || exn = Exit then 0 else 1),
+                 exn <> Exit, Printexc.to_string exn))
+    [ Not_found; Failure "x"; Invalid_argument "y"; Sys_error "z"; Exit ])
+
+external error_exn: int -> exn option = "error_exn"
+
+let%test "exn from error" = Util.check_leaks (fun () ->
+  error_exn 0 = Some Not_found &&
+  error_exn 1 = Some (Failure "x") &&
+  error_exn 2 = Some (Assert_failure ("a.ml", 1, 2)) &&
+  error_exn 3 = Some Exit)
+
+let%test "exn message" = Util.check_leaks (fun () ->
+  exn_message Not_found = Some "not found" &&
+  exn_message (Failure "x") = Some "x" &&
+  exn_message (Invalid_argument "y") = Some "y" &&
+  exn_message (Sys_error "z") = None &&
+  exn_message Exit = None)
+
+external call_backtrace: (unit -> unit) -> bool -> string list option = "call_backtrace"
+
+let%test "call backtrace" = Util.check_leaks (fun () ->
+  let f () = failwith "backtrace" in
+  let recording = Printexc.backtrace_status () in
+  Printexc.record_backtrace true;
+  let captured = call_backtrace f true in
+  let disabled = call_backtrace f false in
+  Printexc.record_backtrace false;
+  let not_recording = call_backtrace f true in
+  Printexc.record_backtrace recording;
+  disabled = None && not_recording = None &&
+  match captured with
+  | Some (frame :: _) -> String.length frame > 9 && String.sub frame 0 9 = "Raised at"
+  | _ -> false)
+
+external parse_config: string -> int = "parse_config"
+
+let%test "rust error" = Util.check_leaks (fun () ->
+  parse_config "123" = 123 &&
+  match parse_config "abc" with
+  | _ -> false
+  | exception Rust_error.Rust_error e ->
+    Util.gc ();
+    Rust_error.is "ConfigError" e &&
+    e.message = "invalid config" &&
+    e.causes = ["invalid digit found in string"] &&
+    Rust_error.to_string e = "invalid config: invalid digit found in string")
+
+exception Parse_error of int * string
+
+exception Empty
+
+let () = Callback.register_exception "Parse_error" (Parse_error (0, ""))
+let () = Callback.register_exception "Empty" Empty
+
+external parse_number: string -> int = "parse_number"
+external catch_parse_error: (string -> int) -> string -> string = "catch_parse_error"
+external decode_parse_error: (string -> int) -> string -> string = "decode_parse_error"
+
+let%test "derived exception" = Util.check_leaks (fun () ->
+  parse_number " 12 " = 12
+  && (try ignore (parse_number ""); false with Empty -> true)
+  && (try ignore (parse_number "abc"); false with Parse_error (1, _) -> Util.gc (); true)
+)
+
+let%test "decode derived exception" = Util.check_leaks (fun () ->
+  catch_parse_error parse_number "1" = "ok"
+  && catch_parse_error parse_number "" = "empty"
+  && catch_parse_error (fun _ -> raise (Parse_error (3, "bad"))) "" = "3: bad"
+  && catch_parse_error (fun _ -> raise Not_found) "" = "other"
+)
+
+let%test "unrelated exception result" = Util.check_leaks (fun () ->
+  decode_parse_error parse_number "7" = "7"
+  && decode_parse_error parse_number "abc" = "1: invalid digit found in string"
+  && decode_parse_error (fun _ -> failwith "unrelated") "" = "other"
+  && decode_parse_error (fun _ -> raise Not_found) "" = "other"
+)
+
 (* Hash variant *)
 type hash_variant = [
   | `Abc of int
@@ -74,5 +226,20 @@ let%test "test call named" = Util.check_leaks (fun () ->
   x = 4.0
 )
 
+external test_call_named_typed : float -> float = "test_call_named_typed"
+external test_call_named_missing : unit -> string = "test_call_named_missing"
+
+let%test "test call named typed" = Util.check_leaks (fun () ->
+  let x = test_call_named_typed 2.0 in
+  Util.gc ();
+  let y = test_call_named_typed 3.0 in
+  x = 4.0 && y = 6.0
+)
+
+let%test "test named missing" = Util.check_leaks (fun () ->
+  test_call_named_missing ()
+  = "named value not_registered has not been registered using Callback.register"
+)
+
 external func : unit -> unit = "bench_func"
 external native_func : unit -> unit = "bench_native_func"
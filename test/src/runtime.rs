@@ -56,6 +56,216 @@ pub fn raise_failure() -> Result<(), ocaml::Error> {
     ocaml::Error::failwith("An error")
 }
 
+#[ocaml::func]
+pub fn raise_failure_fmt(name: &str, line: ocaml::Int) -> Result<(), ocaml::Error> {
+    ocaml::failwith!("{}:{}: unexpected token", name, line)
+}
+
+#[ocaml::func]
+pub fn raise_invalid_argument(arg: String) -> Result<(), ocaml::Error> {
+    ocaml::Error::invalid_argument(format!("bad argument: {}", arg))
+}
+
+#[ocaml::func]
+pub fn raise_sys_error(path: String) -> Result<(), ocaml::Error> {
+    ocaml::Error::sys_error(path + ": No such file or directory")
+}
+
+#[ocaml::func]
+pub fn raise_predefined(name: &str) -> Result<(), ocaml::Error> {
+    use ocaml::CamlError::*;
+    let file = || std::borrow::Cow::Owned("test.ml".to_string());
+    let err = match name {
+        "Not_found" => NotFound,
+        "Out_of_memory" => OutOfMemory,
+        "Stack_overflow" => StackOverflow,
+        "End_of_file" => EndOfFile,
+        "Division_by_zero" => ZeroDivide,
+        "Sys_blocked_io" => SysBlockedIo,
+        "Failure" => Failure(name.to_string().into()),
+        "Invalid_argument" => InvalidArgument(name.to_string().into()),
+        "Sys_error" => SysError(name.to_string().into()),
+        "Exit" => Exit,
+        "Match_failure" => MatchFailure(file(), 1, 2),
+        "Assert_failure" => AssertFailure(file(), 3, 4),
+        "Undefined_recursive_module" => UndefinedRecursiveModule(file(), 5, 6),
+        _ => return Ok(()),
+    };
+    Err(err.into())
+}
+
+#[ocaml::func]
+pub unsafe fn catch_predefined(f: Value) -> String {
+    use ocaml::CamlError::*;
+    let err = match f.call(gc, Value::unit()) {
+        Ok(_) => return "ok".to_string(),
+        Err(ocaml::Error::Caml(err)) => err,
+        Err(_) => return "error".to_string(),
+    };
+    match err {
+        NotFound => "Not_found".to_string(),
+        OutOfMemory => "Out_of_memory".to_string(),
+        StackOverflow => "Stack_overflow".to_string(),
+        EndOfFile => "End_of_file".to_string(),
+        ZeroDivide => "Division_by_zero".to_string(),
+        SysBlockedIo => "Sys_blocked_io".to_string(),
+        Failure(s) => format!("Failure {}", s),
+        InvalidArgument(s) => format!("Invalid_argument {}", s),
+        SysError(s) => format!("Sys_error {}", s),
+        Exit => "Exit".to_string(),
+        MatchFailure(f, l, c) => format!("Match_failure {} {} {}", f, l, c),
+        AssertFailure(f, l, c) => format!("Assert_failure {} {} {}", f, l, c),
+        UndefinedRecursiveModule(f, l, c) => {
+            format!("Undefined_recursive_module {} {} {}", f, l, c)
+        }
+        _ => "other".to_string(),
+    }
+}
+
+#[ocaml::func]
+pub fn exn_info(exn: ocaml::Exn) -> (String, ocaml::Int, bool, String) {
+    (
+        exn.name().to_string(),
+        exn.arity() as ocaml::Int,
+        exn.is_predefined(),
+        exn.to_string(),
+    )
+}
+
+#[ocaml::func]
+pub unsafe fn call_exn_info(f: Value) -> Option<(String, ocaml::Int, bool, String)> {
+    use ocaml::Exception;
+    let err = f.call(gc, Value::unit()).err()?;
+    let exn = ocaml::Exn::from_error(&err)?;
+    Some((
+        exn.name().to_string(),
+        exn.arity() as ocaml::Int,
+        exn.is_predefined(),
+        exn.to_string(),
+    ))
+}
+
+#[ocaml::func]
+pub fn error_exn(n: ocaml::Int) -> Option<ocaml::Exn> {
+    use ocaml::{CamlError::*, Exception};
+    let err: ocaml::Error = match n {
+        0 => NotFound,
+        1 => Failure("x".into()),
+        2 => AssertFailure("a.ml".into(), 1, 2),
+        _ => Exit,
+    }
+    .into();
+    ocaml::Exn::from_error(&err)
+}
+
+#[ocaml::func]
+pub fn exn_message(exn: ocaml::Exn) -> Option<String> {
+    if exn.is_not_found() {
+        return Some("not found".to_string());
+    }
+    exn.failure()
+        .or_else(|| exn.invalid_argument())
+        .map(|s| s.to_string())
+}
+
+#[ocaml::func]
+pub unsafe fn call_backtrace(f: Value, capture: bool) -> Option<Vec<String>> {
+    ocaml::runtime::capture_backtraces(capture);
+    let res = f.call(gc, Value::unit());
+    ocaml::runtime::capture_backtraces(false);
+    match res {
+        Err(e) => {
+            let bt = e.caml_backtrace()?;
+            assert!(format!("{:?}", e).contains("OCaml backtrace:"));
+            Some(bt.frames().to_vec())
+        }
+        Ok(_) => None,
+    }
+}
+
+#[derive(Debug)]
+pub struct ConfigError(std::num::ParseIntError);
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "invalid config")
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+#[ocaml::func]
+pub fn parse_config(s: &str) -> Result<ocaml::Int, ocaml::Error> {
+    Ok(s.parse().map_err(ConfigError)?)
+}
+
+#[derive(Debug, ocaml::Exception)]
+pub enum ParseError {
+    #[ocaml(name = "Parse_error")]
+    Syntax {
+        line: ocaml::Int,
+        message: String,
+    },
+    Empty,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ParseError::Syntax { line, message } => write!(f, "{}: {}", line, message),
+            ParseError::Empty => write!(f, "empty"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[ocaml::func]
+pub fn parse_number(s: &str) -> Result<ocaml::Int, ocaml::Raise<ParseError>> {
+    if s.trim().is_empty() {
+        return Err(ParseError::Empty.into());
+    }
+
+    let n = s
+        .trim()
+        .parse()
+        .map_err(|e: std::num::ParseIntError| ParseError::Syntax {
+            line: 1,
+            message: e.to_string(),
+        })?;
+    Ok(n)
+}
+
+#[ocaml::func]
+pub unsafe fn catch_parse_error(f: Value, s: Value) -> String {
+    use ocaml::Exception;
+    match f.call(gc, s) {
+        Ok(_) => "ok".to_string(),
+        Err(e) => match ParseError::from_error(&e) {
+            Some(ParseError::Syntax { line, message }) => format!("{}: {}", line, message),
+            Some(ParseError::Empty) => "empty".to_string(),
+            None => "other".to_string(),
+        },
+    }
+}
+
+#[ocaml::func]
+pub unsafe fn decode_parse_error(f: Value, s: Value) -> String {
+    use ocaml::{Exception, FromValue};
+    let res = Value::new(ocaml::sys::caml_callback_exn(f.0, s.0));
+    match Result::<ocaml::Int, ocaml::Error>::from_value(res) {
+        Ok(n) => n.to_string(),
+        Err(e) => match ParseError::from_error(&e) {
+            Some(e) => e.to_string(),
+            None => "other".to_string(),
+        },
+    }
+}
+
 #[ocaml::func]
 pub unsafe fn hash_variant_abc(i: ocaml::Int) -> Value {
     Value::hash_variant(gc, "Abc", Some(Value::int(i)))
@@ -84,9 +294,24 @@ pub unsafe fn test_call_named(g: ocaml::Float) -> OCaml<'_, ocaml::Float> {
     })
 }
 
+#[ocaml::func]
+pub fn test_call_named_typed(g: ocaml::Float) -> Result<ocaml::Float, ocaml::Error> {
+    ocaml::named!("call_named": ocaml::Fn1<ocaml::Float, ocaml::Float>).call(gc, g)
+}
+
+#[ocaml::func]
+pub fn test_call_named_missing() -> String {
+    match ocaml::named!("not_registered": ocaml::Value).value(gc) {
+        Ok(_) => "registered".to_string(),
+        Err(ocaml::Error::Message(s)) => s.to_string(),
+        Err(_) => "error".to_string(),
+    }
+}
+
 #[ocaml::func]
 pub unsafe fn bench_func() {}
 
+#[ocaml::sig("unit -> unit")]
 #[ocaml::native_func]
 pub unsafe fn bench_native_func() -> ocaml::Value {
     ocaml::Value::none()
@@ -25,6 +25,26 @@ let%test "array replace 1" = Util.check_leaks (fun () ->
   (array_replace a 1 "X" = (Some "B")) && (a.(1) = "X")
 )
 
+external float_array_sum: float array -> float = "float_array_sum"
+external float_array_rev: float array -> float array = "float_array_rev"
+external float_array_scale: float array -> float -> float array = "float_array_scale"
+external float_array_swap: float array -> int -> int -> unit = "float_array_swap"
+
+let%test "float array sum" = Util.check_leaks (fun () ->
+  float_array_sum [||] = 0. && float_array_sum [| 1.5; 2.5; 3. |] = 7.)
+let%test "float array rev" = Util.check_leaks (fun () ->
+  let a = float_array_rev [| 1.; 2.; 3. |] in
+  Util.gc ();
+  a = [| 3.; 2.; 1. |] && float_array_rev [||] = [||])
+let%test "float array scale" = Util.check_leaks (fun () ->
+  let a = float_array_scale (Array.init 1000 float_of_int) 2. in
+  Util.gc ();
+  Array.length a = 1000 && a.(999) = 1998.)
+let%test "float array swap" = Util.check_leaks (fun () ->
+  let a = [| 1.; 2.; 3. |] in
+  float_array_swap a 0 2;
+  a = [| 3.; 2.; 1. |] &&
+  (try float_array_swap a 0 3; false with Invalid_argument _ -> true))
 
 external array1_of_string: string -> (int, int8_unsigned_elt, c_layout) Array1.t = "array1_of_string"
 external array1_new: int -> init:int -> (int, int8_unsigned_elt, c_layout) Array1.t = "array1_new"
@@ -59,6 +79,141 @@ let%test "array1 from rust vec" = Util.check_leaks (fun () ->
   a.{4} = 5.
 )
 
+external array1_sum_f64: (float, float64_elt, c_layout) Array1.t -> float = "array1_sum_f64"
+
+let%test "array1 checked kind" = Util.check_leaks (fun () ->
+  let a = Array1.of_array float64 c_layout [| 1.; 2.; 3. |] in
+  let b = Array1.of_array float32 c_layout [| 1.; 2.; 3. |] in
+  let c = Array2.create float64 c_layout 2 2 in
+  array1_sum_f64 a = 6. &&
+  (try ignore (array1_sum_f64 (Obj.magic b)); false with Failure _ -> true) &&
+  (try ignore (array1_sum_f64 (Obj.magic c)); false with Failure _ -> true)
+)
+
+external array1_len_unchecked: (float, float64_elt, c_layout) Array1.t -> int = "array1_len_unchecked"
+
+let%test "array1 unchecked kind" = Util.check_leaks (fun () ->
+  let b = Array1.of_array float32 c_layout [| 1.; 2.; 3. |] in
+  array1_len_unchecked (Obj.magic b) = 3
+)
+
+external array1_from_vec: int -> (int64, int64_elt, c_layout) Array1.t = "array1_from_vec"
+
+let%test "array1 from vec" = Util.check_leaks (fun () ->
+  let a = array1_from_vec 1000 in
+  Array1.blit (array1_from_vec 10) (Array1.sub a 100 10);
+  Util.gc ();
+  Array1.dim a = 1000 &&
+  a.{0} = 0L &&
+  a.{105} = 5L &&
+  a.{999} = 999L &&
+  Array1.dim (array1_from_vec 0) = 0
+)
+
+let%test "array1 from vec sub" = Util.check_leaks (fun () ->
+  let sub = Array1.sub (array1_from_vec 1000) 500 10 in
+  Util.gc ();
+  sub.{0} = 500L && sub.{9} = 509L
+)
+
+type map_mode = Read_only | Shared | Private
+
+external arrayn_map_file: string -> int -> map_mode -> int array -> (int, int8_unsigned_elt, c_layout) Genarray.t = "arrayn_map_file"
+
+let with_temp_file contents f =
+  let path = Filename.temp_file "ocamlrs" ".bin" in
+  let oc = open_out_bin path in
+  output_string oc contents;
+  close_out oc;
+  Fun.protect ~finally:(fun () -> Sys.remove path) (fun () -> f path)
+
+let read_file path =
+  let ic = open_in_bin path in
+  let s = really_input_string ic (in_channel_length ic) in
+  close_in ic;
+  s
+
+let%test "arrayn map file shared" = Util.check_leaks (fun () ->
+  with_temp_file "abcdefgh" (fun path ->
+    let a = arrayn_map_file path 0 Shared [|-1; 2|] in
+    Genarray.set a [|0; 0|] (Char.code 'z');
+    let fd = Unix.openfile path [Unix.O_RDONLY] 0 in
+    let b = array2_of_genarray (Unix.map_file fd char c_layout false [|-1; 2|]) in
+    Unix.close fd;
+    Util.gc ();
+    Genarray.dims a = [|4; 2|] &&
+    Genarray.get a [|3; 1|] = Char.code 'h' &&
+    b.{0, 0} = 'z' &&
+    read_file path = "zbcdefgh"
+  )
+)
+
+let%test "arrayn map file private" = Util.check_leaks (fun () ->
+  with_temp_file "abcdefgh" (fun path ->
+    let a = arrayn_map_file path 2 Private [|-1|] in
+    Genarray.set a [|0|] (Char.code 'z');
+    Util.gc ();
+    Genarray.dims a = [|6|] &&
+    Genarray.get a [|0|] = Char.code 'z' &&
+    read_file path = "abcdefgh"
+  )
+)
+
+let%test "arrayn map file read-only" = Util.check_leaks (fun () ->
+  with_temp_file "abcdefgh" (fun path ->
+    let a = arrayn_map_file path 4 Read_only [|-1|] in
+    Util.gc ();
+    Genarray.dims a = [|4|] &&
+    Genarray.get a [|0|] = Char.code 'e' &&
+    read_file path = "abcdefgh"
+  )
+)
+
+let%test "arrayn map file grow" = Util.check_leaks (fun () ->
+  with_temp_file "abc" (fun path ->
+    let a = arrayn_map_file path 0 Shared [|2; 4|] in
+    Genarray.set a [|1; 3|] (Char.code 'x');
+    Util.gc ();
+    read_file path = "abc\000\000\000\000x" &&
+    (try ignore (arrayn_map_file path 0 Read_only [|16|]); false with Invalid_argument _ -> true) &&
+    (try ignore (arrayn_map_file path 0 Shared [|max_int; max_int|]); false with Invalid_argument _ -> true) &&
+    (try ignore (arrayn_map_file path 0 Private [|3; -1|]); false with Invalid_argument _ -> true)
+  )
+)
+
+external array1_conj: (Complex.t, complex64_elt, c_layout) Array1.t -> unit = "array1_conj"
+external array1_complex32: int -> (Complex.t, complex32_elt, c_layout) Array1.t = "array1_complex32"
+external array1_nativeint_sum: (nativeint, nativeint_elt, c_layout) Array1.t -> int = "array1_nativeint_sum"
+external array1_int_double: (int, int_elt, c_layout) Array1.t -> unit = "array1_int_double"
+
+let%test "array1 complex64" = Util.check_leaks (fun () ->
+  let a = Array1.of_array complex64 c_layout [| Complex.one; Complex.i; { Complex.re = 2.; im = -3. } |] in
+  array1_conj a;
+  Util.gc ();
+  a.{0} = Complex.one &&
+  a.{1} = Complex.neg Complex.i &&
+  a.{2} = { Complex.re = 2.; im = 3. }
+)
+
+let%test "array1 complex32" = Util.check_leaks (fun () ->
+  let a = array1_complex32 3 in
+  Util.gc ();
+  Array1.dim a = 3 &&
+  a.{0} = Complex.zero &&
+  a.{2} = { Complex.re = 2.; im = -2. }
+)
+
+let%test "array1 nativeint" = Util.check_leaks (fun () ->
+  let a = Array1.of_array nativeint c_layout [| 1n; -2n; 40n |] in
+  array1_nativeint_sum a = 39
+)
+
+let%test "array1 int" = Util.check_leaks (fun () ->
+  let a = Array1.of_array int c_layout [| 1; -2; max_int / 2 |] in
+  array1_int_double a;
+  a.{0} = 2 && a.{1} = -4 && a.{2} = max_int - 1
+)
+
 type array2_t = (float, float32_elt, c_layout) Array2.t
 
 external make_array2: int -> int -> array2_t = "make_array2"
@@ -93,6 +248,88 @@ let%test "array2_format" = Util.check_leaks (fun () ->
   check && (array2_format arr) = "[[0, 0, 0], [0, 1, 2], [0, 2, 4]]"
 )
 
+type 'l arrayn_t = (float, float64_elt, 'l) Genarray.t
+
+external arrayn_create: int array -> bool -> 'l arrayn_t = "arrayn_create"
+external arrayn_get: 'l arrayn_t -> int array -> float = "arrayn_get"
+external arrayn_sub: 'l arrayn_t -> int -> int -> 'l arrayn_t = "arrayn_sub"
+external arrayn_slice: 'l arrayn_t -> int array -> 'l arrayn_t = "arrayn_slice"
+external arrayn_reshape: 'l arrayn_t -> int array -> 'l arrayn_t = "arrayn_reshape"
+
+let%test "arrayn c layout" = Util.check_leaks (fun () ->
+  let (arr : c_layout arrayn_t) = arrayn_create [|2; 3; 4|] false in
+  Util.gc ();
+  Genarray.dims arr = [|2; 3; 4|] &&
+  Genarray.get arr [|1; 2; 3|] = 23. &&
+  Genarray.get arr [|1; 0; 2|] = 14. &&
+  arrayn_get arr [|1; 2; 3|] = 23.
+)
+
+let%test "arrayn fortran layout" = Util.check_leaks (fun () ->
+  let (arr : fortran_layout arrayn_t) = arrayn_create [|2; 3|] true in
+  Util.gc ();
+  Genarray.dims arr = [|2; 3|] &&
+  Genarray.get arr [|2; 1|] = 1. &&
+  Genarray.get arr [|1; 2|] = 2. &&
+  arrayn_get arr [|1; 0|] = 1. &&
+  arrayn_get arr [|0; 1|] = 2.
+)
+
+let%test "arrayn view" = Util.check_leaks (fun () ->
+  let arr = Genarray.create float64 fortran_layout [|3; 2|] in
+  for i = 1 to 3 do
+    for j = 1 to 2 do
+      Genarray.set arr [|i; j|] (float_of_int (i * 10 + j))
+    done
+  done;
+  arrayn_get arr [|2; 1|] = 32. && arrayn_get arr [|0; 1|] = 12.
+)
+
+let%test "arrayn sub" = Util.check_leaks (fun () ->
+  let (arr : c_layout arrayn_t) = arrayn_create [|4; 2|] false in
+  let sub = arrayn_sub arr 1 2 in
+  let (farr : fortran_layout arrayn_t) = arrayn_create [|2; 4|] true in
+  let fsub = arrayn_sub farr 1 2 in
+  Util.gc ();
+  Genarray.dims sub = [|2; 2|] &&
+  Genarray.get sub [|0; 0|] = 2. &&
+  Genarray.dims fsub = [|2; 2|] &&
+  Genarray.get fsub [|1; 1|] = 2. &&
+  (try ignore (arrayn_sub arr 3 2); false with Invalid_argument _ -> true)
+)
+
+let%test "arrayn slice" = Util.check_leaks (fun () ->
+  let (arr : c_layout arrayn_t) = arrayn_create [|2; 3; 4|] false in
+  let s = arrayn_slice arr [|1|] in
+  Genarray.set s [|0; 0|] 100.;
+  Util.gc ();
+  Genarray.dims s = [|3; 4|] &&
+  Genarray.get s [|2; 3|] = 23. &&
+  Genarray.get arr [|1; 0; 0|] = 100. &&
+  (try ignore (arrayn_slice arr [|2|]); false with Invalid_argument _ -> true)
+)
+
+external arrayn_from_vec: int array -> bool -> 'l arrayn_t = "arrayn_from_vec"
+
+let%test "arrayn from vec" = Util.check_leaks (fun () ->
+  let (arr : fortran_layout arrayn_t) = arrayn_from_vec [|2; 3|] true in
+  let r = Genarray.slice_right arr [|3|] in
+  Util.gc ();
+  Genarray.dims arr = [|2; 3|] &&
+  Genarray.get arr [|2; 1|] = 1. &&
+  Genarray.dims r = [|2|] &&
+  Genarray.get r [|2|] = 5.
+)
+
+let%test "arrayn reshape" = Util.check_leaks (fun () ->
+  let (arr : c_layout arrayn_t) = arrayn_create [|2; 3|] false in
+  let r = arrayn_reshape arr [|3; 2|] in
+  Util.gc ();
+  Genarray.dims r = [|3; 2|] &&
+  Genarray.get r [|2; 1|] = 5. &&
+  (try ignore (arrayn_reshape arr [|4; 2|]); false with Invalid_argument _ -> true)
+)
+
 type abstract_ptr
 
 external alloc_abstract_pointer : float -> abstract_ptr = "alloc_abstract_pointer"
@@ -106,3 +343,163 @@ let%test "abstract pointer" = Util.check_leaks (fun () ->
   Util.gc ();
   abstract_pointer_free a; f = 1.5
 )
+
+type polymorphic = [ `A | `B of int | `C of int * string ]
+
+external polymorphic_incr: polymorphic -> polymorphic = "polymorphic_incr"
+
+let%test "polymorphic variant a" = Util.check_leaks (fun () -> polymorphic_incr `A = `B 0)
+let%test "polymorphic variant b" = Util.check_leaks (fun () -> polymorphic_incr (`B 1) = `C (2, "1"))
+let%test "polymorphic variant c" = Util.check_leaks (fun () ->
+  let p = polymorphic_incr (`C (5, "abc")) in
+  Util.gc ();
+  p = `C (6, "abc")
+)
+
+type struct2 = {
+  b: string;
+  a: int;
+  c: int;
+}
+
+external make_struct2: int -> int -> struct2 = "make_struct2"
+external struct2_sum: struct2 -> int = "struct2_sum"
+
+let%test "struct2 layout" = Util.check_leaks (fun () ->
+  let s = make_struct2 1 2 in
+  Util.gc ();
+  s = { b = "2"; a = 1; c = 3 }
+)
+
+let%test "struct2 sum" = Util.check_leaks (fun () -> struct2_sum { b = "10"; a = 5; c = 1 } = 16)
+
+(* Older layout of [struct2], before [c] was added *)
+type struct2_v1 = {
+  b: string;
+  a: int;
+}
+
+external struct2_v1_sum: struct2_v1 -> int = "struct2_sum"
+
+let%test "struct2 default" = Util.check_leaks (fun () -> struct2_v1_sum { b = "10"; a = 5 } = 14)
+
+type shape =
+  | Point
+  | Circle of { radius: float }
+  | Rect of { width: float; height: float; label: string }
+
+external shape_describe: Obj.t -> string = "shape_describe"
+
+let%test "try from value" = Util.check_leaks (fun () ->
+  shape_describe (Obj.repr Point) = "point" &&
+  shape_describe (Obj.repr (Circle { radius = 1.5 })) = "circle 1.5" &&
+  shape_describe (Obj.repr (Rect { width = 2.; height = 3.; label = "r" })) = "r 2x3"
+)
+
+let%test "try from value errors" = Util.check_leaks (fun () ->
+  let field = shape_describe (Obj.repr (ref (Circle { radius = 1.5 }))) in
+  let size = shape_describe (Obj.repr (Circle { radius = 1.5 }, 0)) in
+  let tag = shape_describe (Obj.new_block 3 1) in
+  let constant = shape_describe (Obj.repr 3) in
+  Util.gc ();
+  field = "field .Circle.radius: expected float, got block tag 0 of size 1"
+  && size = "expected block tag 0 of size 1, got block tag 0 of size 2"
+  && tag = "expected a constructor of Shape, got block tag 3 of size 1"
+  && constant = "expected a constructor of Shape, got int"
+)
+
+type inline_record =
+  | Empty
+  | Point of { x: float; y: float }
+  | Named of { name: string; id: int; score: float }
+  | Wrapped of int
+
+external inline_record_scale: inline_record -> float -> inline_record = "inline_record_scale"
+external inline_record_check: Obj.t -> string = "inline_record_check"
+
+let%test "inline record empty" = Util.check_leaks (fun () ->
+  inline_record_scale Empty 2. = Point { x = 2.; y = 2. }
+)
+
+let%test "inline record floats" = Util.check_leaks (fun () ->
+  let p = inline_record_scale (Point { x = 1.5; y = -1. }) 2. in
+  Util.gc ();
+  p = Point { x = 3.; y = -2. }
+)
+
+let%test "inline record mixed" = Util.check_leaks (fun () ->
+  let n = inline_record_scale (Named { name = "ab"; id = 1; score = 0.5 }) 4. in
+  Util.gc ();
+  n = Named { name = "abab"; id = 2; score = 2. }
+  && inline_record_scale (Wrapped 3) 2. = Wrapped 6
+)
+
+let%test "inline record check" = Util.check_leaks (fun () ->
+  inline_record_check (Obj.repr (Point { x = 1.; y = 2. })) = "point 1 2"
+  && inline_record_check (Obj.repr (Named { name = "a"; id = 1; score = 0. })) = "ok"
+  && inline_record_check (Obj.repr [| 1.; 2. |])
+     = "expected a constructor of InlineRecord, got float array"
+)
+
+type meters = float
+type extent = { width: float; height: float }
+
+external meters_extent: meters -> extent = "meters_extent"
+
+let%test "single variant enum" = Util.check_leaks (fun () ->
+  let e = meters_extent 1.5 in
+  Util.gc ();
+  e = { width = 1.5; height = 3. }
+)
+
+type ('a, 'b) pair = { first: 'a; second: 'b }
+
+external pair_swap: (string, int) pair -> (int, string) pair = "pair_swap"
+
+let%test "generic record" = Util.check_leaks (fun () ->
+  let p = pair_swap { first = "a"; second = 1 } in
+  Util.gc ();
+  p = { first = 1; second = "a" }
+)
+
+type 'a tagged = { id: int }
+
+external tagged_next: 'a tagged -> 'a tagged = "tagged_next"
+
+let%test "phantom field" = Util.check_leaks (fun () ->
+  (tagged_next { id = 1 }).id = 2
+)
+
+type level = Off | Low | Medium | High | Max
+
+external level_next: level -> level = "level_next"
+external level_check: level -> string = "level_check"
+
+let%test "explicit discriminants" = Util.check_leaks (fun () ->
+  level_next Low = High && level_next High = Max && level_next Max = Low
+  && level_check Low = "low"
+  && level_check Off = "expected a constructor of Level, got int"
+)
+
+type mixed =
+  | E | A | Unused_2 | Unused_3 | C
+  | Unused_block_0 of unit | Unused_block_1 of unit | B of int
+  | Unused_block_3 of unit | Unused_block_4 of unit | D of string
+
+external mixed_next: mixed -> mixed = "mixed_next"
+
+let%test "mixed discriminants" = Util.check_leaks (fun () ->
+  let d = mixed_next (B 7) in
+  Util.gc ();
+  mixed_next A = B 1 && d = D "7" && mixed_next d = C && mixed_next C = E && mixed_next E = A
+)
+
+type event = Key of string | Click of int | Quit
+
+external event_next: event -> event = "event_next"
+
+let%test "explicit tags" = Util.check_leaks (fun () ->
+  let k = event_next (Click 12) in
+  Util.gc ();
+  k = Key "12" && event_next (Key "abc") = Click 3 && event_next Quit = Quit
+)
@@ -46,6 +46,37 @@ pub unsafe fn array_replace(
     Ok(Some(y))
 }
 
+#[ocaml::func]
+pub fn float_array_sum(arr: ocaml::FloatArray) -> f64 {
+    match arr.as_slice() {
+        Some(s) => s.iter().sum(),
+        None => arr.iter().sum(),
+    }
+}
+
+#[ocaml::func]
+pub fn float_array_rev(mut v: Vec<f64>) -> Vec<f64> {
+    v.reverse();
+    v
+}
+
+#[ocaml::func]
+pub fn float_array_scale(v: ocaml::FloatArray, k: f64) -> Box<[f64]> {
+    v.iter().map(|x| x * k).collect()
+}
+
+#[ocaml::func]
+pub fn float_array_swap(
+    mut arr: ocaml::Array<f64>,
+    i: ocaml::Uint,
+    j: ocaml::Uint,
+) -> Result<(), ocaml::Error> {
+    let x = arr.get(i)?;
+    let y = arr.get(j)?;
+    arr.set(gc, i, y)?;
+    arr.set(gc, j, x)
+}
+
 #[ocaml::func]
 pub unsafe fn array1_of_string(x: &mut str) -> ocaml::bigarray::Array1<u8> {
     ocaml::bigarray::Array1::of_slice(gc, x.as_bytes_mut())
@@ -66,6 +97,94 @@ pub fn array1_from_rust_vec() -> ocaml::bigarray::Array1<f32> {
     ocaml::bigarray::Array1::from_slice(gc, &[1f32, 2f32, 3f32, 4f32, 5f32])
 }
 
+#[ocaml::func]
+pub fn array1_sum_f64(arr: ocaml::bigarray::Array1<f64>) -> f64 {
+    arr.data().iter().sum()
+}
+
+#[ocaml::func(gc, unchecked)]
+pub fn array1_len_unchecked(arr: ocaml::bigarray::Array1<f64>) -> usize {
+    arr.len()
+}
+
+#[ocaml::func]
+pub fn array1_from_vec(n: usize) -> ocaml::bigarray::Array1<i64> {
+    let data: Vec<i64> = (0..n as i64).collect();
+    ocaml::bigarray::Array1::from_vec(gc, data)
+}
+
+#[ocaml::func]
+pub fn arrayn_from_vec(
+    dims: Vec<usize>,
+    fortran: bool,
+) -> Result<ocaml::bigarray::ArrayN<f64>, ocaml::Error> {
+    let layout = if fortran {
+        ocaml::bigarray::Layout::Fortran
+    } else {
+        ocaml::bigarray::Layout::C
+    };
+    let n = dims.iter().product::<usize>();
+    let data: Vec<f64> = (0..n).map(|i| i as f64).collect();
+    ocaml::bigarray::ArrayN::from_vec(gc, data, &dims, layout)
+}
+
+#[ocaml::func]
+pub fn arrayn_map_file(
+    path: &str,
+    pos: usize,
+    mode: ocaml::Int,
+    dims: Vec<ocaml::Int>,
+) -> Result<ocaml::bigarray::ArrayN<u8>, ocaml::Error> {
+    let mode = match mode {
+        0 => ocaml::bigarray::MapMode::ReadOnly,
+        1 => ocaml::bigarray::MapMode::Shared,
+        _ => ocaml::bigarray::MapMode::Private,
+    };
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(mode == ocaml::bigarray::MapMode::Shared)
+        .open(path)?;
+    let dims: Vec<_> = dims
+        .into_iter()
+        .map(|d| if d < 0 { None } else { Some(d as usize) })
+        .collect();
+    ocaml::bigarray::ArrayN::map_file(
+        gc,
+        &file,
+        pos as u64,
+        mode,
+        &dims,
+        ocaml::bigarray::Layout::C,
+    )
+}
+
+#[ocaml::func]
+pub fn array1_conj(mut arr: ocaml::bigarray::Array1<ocaml::bigarray::Complex<f64>>) {
+    for x in arr.data_mut() {
+        x.im = -x.im;
+    }
+}
+
+#[ocaml::func]
+pub fn array1_complex32(n: usize) -> ocaml::bigarray::Array1<ocaml::bigarray::Complex<f32>> {
+    let data: Vec<_> = (0..n)
+        .map(|i| ocaml::bigarray::Complex::new(i as f32, -(i as f32)))
+        .collect();
+    ocaml::bigarray::Array1::from_slice(gc, data)
+}
+
+#[ocaml::func]
+pub fn array1_nativeint_sum(arr: ocaml::bigarray::Array1<isize>) -> ocaml::Int {
+    arr.data().iter().sum()
+}
+
+#[ocaml::func]
+pub fn array1_int_double(mut arr: ocaml::bigarray::Array1<ocaml::bigarray::CamlInt>) {
+    for x in arr.data_mut() {
+        x.0 *= 2;
+    }
+}
+
 #[ocaml::func]
 pub fn make_array2(dim1: usize, dim2: usize) -> ocaml::bigarray::Array2<f32> {
     let arr = ndarray::Array2::zeros((dim1, dim2));
@@ -93,6 +212,57 @@ pub fn array2_format(arr: ocaml::bigarray::Array2<f32>) -> String {
     format!("{}", arr.view()).replace("\n", "")
 }
 
+#[ocaml::func]
+pub fn arrayn_create(
+    dims: Vec<usize>,
+    fortran: bool,
+) -> Result<ocaml::bigarray::ArrayN<f64>, ocaml::Error> {
+    let layout = if fortran {
+        ocaml::bigarray::Layout::Fortran
+    } else {
+        ocaml::bigarray::Layout::C
+    };
+    let mut arr = ocaml::bigarray::ArrayN::create(gc, &dims, layout)?;
+    for (i, x) in arr.data_mut().iter_mut().enumerate() {
+        *x = i as f64;
+    }
+    Ok(arr)
+}
+
+#[ocaml::func]
+pub fn arrayn_get(
+    arr: ocaml::bigarray::ArrayN<f64>,
+    index: Vec<usize>,
+) -> ocaml::OCaml<ocaml::interop::OCamlFloat> {
+    let view = arr.view();
+    ocaml::interop::to_ocaml!(gc, view[ndarray::IxDyn(&index)])
+}
+
+#[ocaml::func]
+pub fn arrayn_sub(
+    arr: ocaml::bigarray::ArrayN<f64>,
+    offset: usize,
+    len: usize,
+) -> Result<ocaml::bigarray::ArrayN<f64>, ocaml::Error> {
+    arr.sub(gc, offset, len)
+}
+
+#[ocaml::func]
+pub fn arrayn_slice(
+    arr: ocaml::bigarray::ArrayN<f64>,
+    index: Vec<usize>,
+) -> Result<ocaml::bigarray::ArrayN<f64>, ocaml::Error> {
+    arr.slice(gc, &index)
+}
+
+#[ocaml::func]
+pub fn arrayn_reshape(
+    arr: ocaml::bigarray::ArrayN<f64>,
+    dims: Vec<usize>,
+) -> Result<ocaml::bigarray::ArrayN<f64>, ocaml::Error> {
+    arr.reshape(gc, &dims)
+}
+
 #[derive(Debug)]
 struct Abstract {
     f: f64,
@@ -115,3 +285,241 @@ pub unsafe fn abstract_pointer_free(f: Value) {
     let f = f.abstract_ptr_val_mut::<Abstract>();
     Box::from_raw(f);
 }
+
+#[derive(ocaml::IntoValue, ocaml::FromValue)]
+#[ocaml(polymorphic)]
+enum Polymorphic {
+    A,
+    B(ocaml::Int),
+    C(ocaml::Int, String),
+}
+
+#[ocaml::func]
+pub fn polymorphic_incr(p: Polymorphic) -> Polymorphic {
+    match p {
+        Polymorphic::A => Polymorphic::B(0),
+        Polymorphic::B(i) => Polymorphic::C(i + 1, i.to_string()),
+        Polymorphic::C(i, s) => Polymorphic::C(i + 1, s),
+    }
+}
+
+mod int_as_string {
+    pub fn into_value(i: ocaml::Int, gc: &ocaml::Runtime) -> ocaml::Value {
+        ocaml::IntoValue::into_value(i.to_string(), gc)
+    }
+
+    pub fn from_value(v: ocaml::Value) -> ocaml::Int {
+        let s: String = ocaml::FromValue::from_value(v);
+        s.parse().unwrap_or_default()
+    }
+}
+
+#[derive(ocaml::IntoValue, ocaml::FromValue)]
+struct Struct2 {
+    #[ocaml(index = 1)]
+    a: ocaml::Int,
+    #[ocaml(index = 0, with = "int_as_string")]
+    b: ocaml::Int,
+    #[ocaml(skip)]
+    cache: Option<String>,
+    #[ocaml(default = "-1")]
+    c: ocaml::Int,
+}
+
+#[ocaml::func]
+pub fn make_struct2(a: ocaml::Int, b: ocaml::Int) -> Struct2 {
+    Struct2 {
+        a,
+        b,
+        cache: Some(format!("{}{}", a, b)),
+        c: a + b,
+    }
+}
+
+#[ocaml::func]
+pub fn struct2_sum(s: Struct2) -> ocaml::Int {
+    assert!(s.cache.is_none());
+    s.a + s.b + s.c
+}
+
+#[derive(ocaml::TryFromValue)]
+enum Shape {
+    Point,
+    Circle {
+        radius: f64,
+    },
+    Rect {
+        width: f64,
+        height: f64,
+        label: String,
+    },
+}
+
+#[ocaml::func]
+pub fn shape_describe(v: ocaml::Value) -> String {
+    match ocaml::TryFromValue::try_from_value(v) {
+        Ok(Shape::Point) => "point".to_string(),
+        Ok(Shape::Circle { radius }) => format!("circle {}", radius),
+        Ok(Shape::Rect {
+            width,
+            height,
+            label,
+        }) => format!("{} {}x{}", label, width, height),
+        Err(e) => e.to_string(),
+    }
+}
+
+#[derive(ocaml::IntoValue, ocaml::FromValue, ocaml::TryFromValue)]
+pub enum InlineRecord {
+    Empty,
+    Point {
+        x: f64,
+        y: f64,
+    },
+    Named {
+        name: String,
+        id: ocaml::Int,
+        score: f64,
+    },
+    Wrapped(ocaml::Int),
+}
+
+#[ocaml::func]
+pub fn inline_record_scale(r: InlineRecord, k: f64) -> InlineRecord {
+    match r {
+        InlineRecord::Empty => InlineRecord::Point { x: k, y: k },
+        InlineRecord::Point { x, y } => InlineRecord::Point { x: x * k, y: y * k },
+        InlineRecord::Named { name, id, score } => InlineRecord::Named {
+            name: name.repeat(2),
+            id: id + 1,
+            score: score * k,
+        },
+        InlineRecord::Wrapped(i) => InlineRecord::Wrapped(i * k as ocaml::Int),
+    }
+}
+
+#[ocaml::func]
+pub fn inline_record_check(v: ocaml::Value) -> String {
+    match ocaml::TryFromValue::try_from_value(v) {
+        Ok(InlineRecord::Point { x, y }) => format!("point {} {}", x, y),
+        Ok(_) => "ok".to_string(),
+        Err(e) => e.to_string(),
+    }
+}
+
+#[derive(ocaml::IntoValue, ocaml::FromValue)]
+pub enum Meters {
+    #[ocaml(unboxed)]
+    Meters(f64),
+}
+
+#[derive(ocaml::IntoValue, ocaml::FromValue)]
+pub enum Extent {
+    #[ocaml(floats_array)]
+    Extent { width: f64, height: f64 },
+}
+
+#[ocaml::func]
+pub fn meters_extent(m: Meters) -> Extent {
+    let Meters::Meters(m) = m;
+    Extent::Extent {
+        width: m,
+        height: m * 2.,
+    }
+}
+
+#[derive(ocaml::IntoValue, ocaml::FromValue, ocaml::TryFromValue)]
+pub struct Pair<A, B> {
+    first: A,
+    second: B,
+}
+
+#[ocaml::func]
+pub fn pair_swap(p: Pair<String, ocaml::Int>) -> Pair<ocaml::Int, String> {
+    Pair {
+        first: p.second,
+        second: p.first,
+    }
+}
+
+#[derive(ocaml::IntoValue, ocaml::FromValue)]
+#[ocaml(bound = "T: 'static")]
+pub struct Tagged<T> {
+    id: ocaml::Int,
+    marker: std::marker::PhantomData<T>,
+}
+
+#[ocaml::func]
+pub fn tagged_next(t: Tagged<std::fs::File>) -> Tagged<std::fs::File> {
+    Tagged {
+        id: t.id + 1,
+        marker: std::marker::PhantomData,
+    }
+}
+
+#[derive(ocaml::IntoValue, ocaml::FromValue, ocaml::TryFromValue)]
+#[ocaml(discriminants)]
+pub enum Level {
+    Low = 1,
+    High = 3,
+    Max,
+}
+
+#[ocaml::func]
+pub fn level_next(l: Level) -> Level {
+    match l {
+        Level::Low => Level::High,
+        Level::High => Level::Max,
+        Level::Max => Level::Low,
+    }
+}
+
+#[ocaml::func]
+pub fn level_check(v: ocaml::Value) -> String {
+    match ocaml::TryFromValue::try_from_value(v) {
+        Ok(Level::Low) => "low".to_string(),
+        Ok(_) => "ok".to_string(),
+        Err(e) => e.to_string(),
+    }
+}
+
+#[derive(ocaml::IntoValue, ocaml::FromValue, ocaml::TryFromValue)]
+#[ocaml(discriminants)]
+#[repr(u8)]
+pub enum Mixed {
+    A = 1,
+    B(ocaml::Int),
+    C = 4,
+    D(String),
+    #[ocaml(tag = 0)]
+    E,
+}
+
+#[ocaml::func]
+pub fn mixed_next(m: Mixed) -> Mixed {
+    match m {
+        Mixed::A => Mixed::B(1),
+        Mixed::B(n) => Mixed::D(n.to_string()),
+        Mixed::D(_) => Mixed::C,
+        Mixed::C => Mixed::E,
+        Mixed::E => Mixed::A,
+    }
+}
+
+#[derive(ocaml::IntoValue, ocaml::FromValue)]
+pub enum Event {
+    #[ocaml(tag = 1)]
+    Click(ocaml::Int),
+    #[ocaml(tag = 0)]
+    Key(String),
+    Quit,
+}
+
+#[ocaml::func]
+pub fn event_next(e: Event) -> Event {
+    match e {
+        Event::Click(n) => Event::Key(n.to_string()),
+        Event::Key(k) => Event::Click(k.len() as ocaml::Int),
+        Event::Quit => Event::Quit,
+    }
+}